@@ -1,80 +1,361 @@
-// Tough for day 1, at least part 2 is - see comment within the main implementation
-// for more thoughts on that.
+// Tough for day 1, at least part 2 is - see comment on the matcher for more thoughts on that.
 // My main issue was not reading isntructions - for some reason I thought part2 required
 // concatenating *all* digits.
 //
-// Useful things I learnt today: the `String::char_indices()` method.
-
-// Get the calibration value looking only for digits.
-fn get_calibration_part1(line: &str) -> u32 {
-    let digits = line
-        .chars()
-        // Nice shortcut here - to_digit() will return None if not a digit, which is
-        // exactly what filter_map() needs to exclude from the resulting iterator.
-        .filter_map(|c| c.to_digit(10))
-        .collect::<Vec<_>>();
-    calculate_calibration(&digits)
+// The digit names are configurable: `--vocab=french,roman` picks from the built in word
+// lists, and `--vocab-file=<path>` loads extra `token digit` pairs, one per line.  Pass
+// `--tokens` to see which tokens were picked as the first and last on each line.
+//
+// Both parts are worked out together in one pass over each line, reading the input a line
+// at a time, so it copes with generated inputs far too big to load.  Use `-` as the input
+// file to read from stdin.
+//
+// Useful things I learnt today: the `String::char_indices()` method, and Aho-Corasick.
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+
+use crate::opts;
+use crate::parse::{ParseError, ParseResult, Span};
+
+// Some text, and the digit it stands for.  Part 1 only counts the numerals.
+#[derive(Debug, Clone)]
+struct Token {
+    text: String,
+    value: u32,
+    numeral: bool,
 }
 
-// Make a 2-digit number from first and last digit.  That's just 10*A + B.
-fn calculate_calibration(digits: &[u32]) -> u32 {
-    if digits.is_empty() {
-        0
-    } else {
-        10 * (digits.first().unwrap()) + digits.last().unwrap()
-    }
+#[derive(Debug, Clone, Default)]
+struct Vocabulary {
+    tokens: Vec<Token>,
 }
 
 // There might be a crate somewhere for parsing digit names, but this is an easy enough
-// alternative.
-const DIGIT_NAMES: [(&str, u32); 9] = [
-    ("one", 1),
-    ("two", 2),
-    ("three", 3),
-    ("four", 4),
-    ("five", 5),
-    ("six", 6),
-    ("seven", 7),
-    ("eight", 8),
-    ("nine", 9),
+// alternative.  Each list is the names for 1 to 9.
+const DIGIT_NAMES: [(&str, [&str; 9]); 5] = [
+    (
+        "english",
+        [
+            "one", "two", "three", "four", "five", "six", "seven", "eight", "nine",
+        ],
+    ),
+    (
+        "french",
+        [
+            "un", "deux", "trois", "quatre", "cinq", "six", "sept", "huit", "neuf",
+        ],
+    ),
+    (
+        "german",
+        [
+            "eins", "zwei", "drei", "vier", "fünf", "sechs", "sieben", "acht", "neun",
+        ],
+    ),
+    (
+        "spanish",
+        [
+            "uno", "dos", "tres", "cuatro", "cinco", "seis", "siete", "ocho", "nueve",
+        ],
+    ),
+    (
+        "roman",
+        ["i", "ii", "iii", "iv", "v", "vi", "vii", "viii", "ix"],
+    ),
 ];
 
-// Get the calibration value, looking for numeric and English-word digits.
-fn get_calibration_part2(line: &str) -> u32 {
-    let mut digits = vec![];
-    for (idx, c) in line.char_indices() {
-        if c.is_ascii_digit() {
-            digits.push(c.to_digit(10).unwrap())
-        } else {
-            // I first thought there was inefficiency here - when we hit the match we could skip
-            // over the next name.len() entries in the loop, and couldn't be bothered to enhance.
-            // But that's wrong, as I found when reading the subreddit - there's a trap that completely
-            // passed me by because of my basic implementation. Digit names can overlap,
-            // as in "fiveight".  So we do need to check all characters.
-            // There is an optimization in that we don't need to find every digit - we could just search
-            // for the first match from each end, or only store 2 and overwrite the second each time we
-            // get a subsequent match.  But getting the full set is fast enough.
-            let substr = line.get(idx..).unwrap();
-            for (name, val) in DIGIT_NAMES {
-                if substr.starts_with(name) {
-                    // A match - add to list.  No need to check any more possible names for this
-                    // character.
-                    digits.push(val);
-                    break;
+impl Vocabulary {
+    // Just the digits themselves.
+    fn numerals() -> Self {
+        Vocabulary {
+            tokens: (0..10)
+                .map(|value| Token {
+                    text: value.to_string(),
+                    value,
+                    numeral: true,
+                })
+                .collect(),
+        }
+    }
+
+    fn named(name: &str) -> Option<Self> {
+        let (_, names) = DIGIT_NAMES.iter().find(|(n, _)| *n == name)?;
+        Some(Vocabulary {
+            tokens: names
+                .iter()
+                .zip(1..)
+                .map(|(text, value)| Token {
+                    text: text.to_string(),
+                    value,
+                    numeral: false,
+                })
+                .collect(),
+        })
+    }
+
+    // `token digit` pairs, one per line.  Blank lines and `#` comments are skipped.
+    fn parse(input: Span) -> ParseResult<Self> {
+        let mut tokens = vec![];
+        for line in input.lines().map(|l| l.trim()) {
+            if line.is_empty() || line.as_str().starts_with('#') {
+                continue;
+            }
+            let [text, value] = line.captures("{} {}")?;
+            let value = value.trim().parse()?;
+            if value > 9 {
+                return Err(line.error(format!("{value} isn't a digit")));
+            }
+            tokens.push(Token {
+                text: text.as_str().to_string(),
+                value,
+                numeral: false,
+            });
+        }
+        Ok(Vocabulary { tokens })
+    }
+
+    fn extend(&mut self, other: Vocabulary) {
+        self.tokens.extend(other.tokens);
+    }
+}
+
+// A token found in a line, as byte offsets.
+#[derive(Debug, Clone, Copy)]
+struct Match {
+    start: usize,
+    end: usize,
+    token: usize,
+}
+
+// My first go at part 2 checked every digit name against every position in the line.
+// I first thought there was inefficiency there - when we hit a match we could skip over
+// the next name.len() characters, and couldn't be bothered to enhance.  But that's wrong,
+// as I found when reading the subreddit - there's a trap that completely passed me by
+// because of my basic implementation.  Digit names can overlap, as in "fiveight".
+//
+// Now it's an Aho-Corasick automaton: a trie of all the tokens, where each state also
+// knows where to go on any byte that doesn't continue a token (to the longest suffix of
+// what we've seen that *is* the start of one).  So one pass over the line finds every
+// match, overlapping or not.  Matching ignores ASCII case.
+struct Matcher {
+    tokens: Vec<Token>,
+    // The next state for each state and input byte, tagged with `HAS_OUTPUT`.
+    transitions: Vec<[u32; 256]>,
+    // Tokens ending at each state - including shorter ones that are suffixes of it.
+    outputs: Vec<Vec<usize>>,
+}
+
+// Top bit of a transition, set if there are any matches in the state it goes to.
+const HAS_OUTPUT: u32 = 1 << 31;
+
+impl Matcher {
+    fn new(vocab: Vocabulary) -> Self {
+        const NONE: u32 = u32::MAX;
+        // Build the trie.
+        let mut transitions = vec![[NONE; 256]];
+        let mut outputs = vec![vec![]];
+        for (idx, token) in vocab.tokens.iter().enumerate() {
+            assert!(!token.text.is_empty(), "Empty token");
+            let mut state = 0;
+            for b in token.text.bytes() {
+                let b = b.to_ascii_lowercase() as usize;
+                if transitions[state][b] == NONE {
+                    transitions[state][b] = transitions.len() as u32;
+                    transitions.push([NONE; 256]);
+                    outputs.push(vec![]);
+                }
+                state = transitions[state][b] as usize;
+            }
+            outputs[state].push(idx);
+        }
+
+        // Fill in the gaps, breadth first so the state we fall back to is always done.
+        let mut fallback = vec![0; transitions.len()];
+        let mut queue = VecDeque::new();
+        for next in transitions[0].iter_mut() {
+            if *next == NONE {
+                *next = 0;
+            } else {
+                queue.push_back(*next as usize);
+            }
+        }
+        while let Some(state) = queue.pop_front() {
+            let inherited = outputs[fallback[state]].clone();
+            outputs[state].extend(inherited);
+            let fallback_row = transitions[fallback[state]];
+            for (next, instead) in transitions[state].iter_mut().zip(fallback_row) {
+                match *next {
+                    NONE => *next = instead,
+                    next => {
+                        fallback[next as usize] = instead as usize;
+                        queue.push_back(next as usize);
+                    }
+                }
+            }
+        }
+
+        // Tidy up the table for the scan: uppercase goes wherever lowercase does, and flag
+        // the transitions into states where something matches, so most bytes don't need to
+        // look at the outputs at all.
+        for row in transitions.iter_mut() {
+            for b in b'A'..=b'Z' {
+                row[b as usize] = row[b.to_ascii_lowercase() as usize];
+            }
+            for next in row.iter_mut() {
+                if !outputs[*next as usize].is_empty() {
+                    *next |= HAS_OUTPUT;
                 }
             }
         }
+
+        Matcher {
+            tokens: vocab.tokens,
+            transitions,
+            outputs,
+        }
+    }
+
+    // Call `found` with every match in the text, in order of where they end.
+    fn scan(&self, text: &[u8], mut found: impl FnMut(Match)) {
+        let mut state = 0;
+        for (idx, &b) in text.iter().enumerate() {
+            let next = self.transitions[state][b as usize];
+            state = (next & !HAS_OUTPUT) as usize;
+            if next & HAS_OUTPUT == 0 {
+                continue;
+            }
+            for &token in &self.outputs[state] {
+                found(Match {
+                    start: idx + 1 - self.tokens[token].text.len(),
+                    end: idx + 1,
+                    token,
+                });
+            }
+        }
+    }
+
+    // The ends of the line for both parts at once: just the numerals, and every token.
+    fn find_ends(&self, line: &[u8]) -> (Ends, Ends) {
+        let mut numerals = Ends::default();
+        let mut all = Ends::default();
+        self.scan(line, |m| {
+            if self.tokens[m.token].numeral {
+                numerals.add(m);
+            }
+            all.add(m);
+        });
+        (numerals, all)
+    }
+
+    // Make a 2-digit number from first and last digit.  That's just 10*A + B.
+    fn calibration(&self, ends: &Ends) -> u64 {
+        match (ends.first, ends.last) {
+            (Some(first), Some(last)) => {
+                (10 * self.tokens[first.token].value + self.tokens[last.token].value) as u64
+            }
+            _ => 0,
+        }
     }
-    calculate_calibration(&digits)
 }
 
-pub fn run(input_path: String) {
-    let input = std::fs::read_to_string(input_path).unwrap();
+// The first and last tokens in a line.  When matches overlap, the first is whichever
+// starts earliest and the last whichever finishes latest, taking the longer one on a tie -
+// so "viii" is 8 rather than 1 at both ends.
+#[derive(Debug, Clone, Copy, Default)]
+struct Ends {
+    first: Option<Match>,
+    last: Option<Match>,
+}
+
+impl Ends {
+    fn add(&mut self, m: Match) {
+        if self
+            .first
+            .is_none_or(|f| (m.start, f.end) < (f.start, m.end))
+        {
+            self.first = Some(m);
+        }
+        if self
+            .last
+            .is_none_or(|l| (m.end, l.start) > (l.end, m.start))
+        {
+            self.last = Some(m);
+        }
+    }
+}
+
+// Work out the vocabulary for part 2 from the options.  Plain English if there aren't any.
+fn part2_vocabulary() -> Result<Vocabulary, String> {
+    let mut vocab = Vocabulary::numerals();
+    let file = opts::value("vocab-file");
+    let names = match opts::value("vocab") {
+        Some(names) => names,
+        None if file.is_some() => String::new(),
+        None => "english".to_string(),
+    };
+    for name in names.split(',').filter(|n| !n.is_empty()) {
+        vocab.extend(Vocabulary::named(name).ok_or_else(|| format!("Unknown vocabulary {name}"))?);
+    }
+    if let Some(path) = file {
+        let text = std::fs::read_to_string(&path).map_err(|e| format!("{path}: {e}"))?;
+        let extra =
+            Vocabulary::parse(Span::new(&text)).map_err(|e: ParseError| format!("{path}: {e}"))?;
+        vocab.extend(extra);
+    }
+    Ok(vocab)
+}
+
+// Add up the calibration values for both parts, a line at a time so the input never has to
+// fit in memory.  Lines are bytes rather than strings so there's no UTF-8 checking to slow
+// things down either.
+fn calibrate(
+    mut input: impl BufRead,
+    matcher: &Matcher,
+    show_tokens: bool,
+) -> io::Result<(u64, u64)> {
+    let mut part1 = 0;
+    let mut part2 = 0;
+    let mut line = vec![];
+    while input.read_until(b'\n', &mut line)? > 0 {
+        let (numerals, all) = matcher.find_ends(line.trim_ascii_end());
+        part1 += matcher.calibration(&numerals);
+        part2 += matcher.calibration(&all);
+        if show_tokens {
+            let show = |m: Option<Match>| match m {
+                Some(m) => format!(
+                    "{}={}",
+                    String::from_utf8_lossy(&line[m.start..m.end]),
+                    matcher.tokens[m.token].value
+                ),
+                None => "-".to_string(),
+            };
+            println!(
+                "{}: first {}, last {}",
+                String::from_utf8_lossy(line.trim_ascii_end()),
+                show(all.first),
+                show(all.last)
+            );
+        }
+        line.clear();
+    }
+    Ok((part1, part2))
+}
+
+pub fn run(input_path: String) -> Result<(), String> {
+    let matcher = Matcher::new(part2_vocabulary()?);
+    let show_tokens = opts::flag("tokens");
 
     // Just need the sum of calibration values each time.
-    let part1 = input.lines().map(get_calibration_part1).sum::<u32>();
-    let part2 = input.lines().map(get_calibration_part2).sum::<u32>();
+    let (part1, part2) = if input_path == "-" {
+        calibrate(std::io::stdin().lock(), &matcher, show_tokens)
+    } else {
+        let file = File::open(&input_path).map_err(|e| format!("{input_path}: {e}"))?;
+        calibrate(BufReader::new(file), &matcher, show_tokens)
+    }
+    .map_err(|e| format!("{input_path}: {e}"))?;
 
     println!("Part 1: {}", part1);
     println!("Part 2: {}", part2);
+    Ok(())
 }
@@ -2,100 +2,143 @@
 // Parsing is the only fiddly bit - I almost reached for regex, but the need for nesting across
 // the various draws for each game felt fiddly, and I quite like the string-split-and-iterator
 // approach when you know the input is well-formed and can just unwrap eveyrwhere.
+// (These days the splitting goes through the shared parse helpers, which at least say where
+// the input was wrong if it isn't well-formed after all.)
+//
+// Cubes can be any colour, not just red, green and blue.  Pass `--bag="7 red, 2 purple"` to
+// check the games against a different bag in part 1.
 
-// A set of numbers read from the bag.
+use std::{collections::BTreeMap, fmt};
+
+use crate::opts;
+use crate::parse::{ParseResult, Span};
+
+// The bag for part 1.
+const PART1_BAG: &str = "12 red, 13 green, 14 blue";
+// The colours that go into a power.
+const POWER_COLOURS: [&str; 3] = ["red", "green", "blue"];
+
+// Some cubes, by colour - either a set read from the bag, or a whole bag.
+// Any colour that isn't there counts as zero.
 #[derive(Debug, Clone, Eq, PartialEq, Default)]
-struct ShowResult {
-    blue: u16,
-    red: u16,
-    green: u16,
+struct Cubes(BTreeMap<String, u32>);
+
+impl Cubes {
+    // Format is: [num] [color], [num] [color], ...
+    fn parse(span: Span) -> ParseResult<Self> {
+        let mut cubes = Cubes::default();
+        for entry in span.split(",").map(|e| e.trim()).filter(|e| !e.is_empty()) {
+            // Should have something of the form {num} {color}
+            let (num, colour) = entry.split_once(" ")?;
+            *cubes
+                .0
+                .entry(colour.trim().as_str().to_string())
+                .or_default() += num.parse::<u32>()?;
+        }
+        Ok(cubes)
+    }
+
+    fn get(&self, colour: &str) -> u32 {
+        self.0.get(colour).copied().unwrap_or(0)
+    }
+
+    // Could these cubes all have come out of `bag` at once?
+    fn fits_in(&self, bag: &Cubes) -> bool {
+        self.0
+            .iter()
+            .all(|(colour, &count)| count <= bag.get(colour))
+    }
+
+    // The smallest set of cubes holding both these and `other`.
+    fn max(mut self, other: &Cubes) -> Cubes {
+        for (colour, &count) in &other.0 {
+            let ours = self.0.entry(colour.clone()).or_default();
+            *ours = (*ours).max(count);
+        }
+        self
+    }
+
+    // The product of the counts of the given colours.
+    fn power(&self, colours: &[&str]) -> u64 {
+        colours.iter().map(|c| self.get(c) as u64).product()
+    }
+}
+
+impl fmt::Display for Cubes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let entries = self
+            .0
+            .iter()
+            .map(|(colour, count)| format!("{count} {colour}"))
+            .collect::<Vec<_>>();
+        write!(f, "{}", entries.join(", "))
+    }
 }
 
 struct Game {
     number: usize,
-    results: Vec<ShowResult>,
+    draws: Vec<Cubes>,
 }
 
 impl Game {
-    fn from_string(index: usize, line: &str) -> Self {
+    fn parse(line: Span) -> ParseResult<Self> {
         // Format is: Game [num]: [num] [color], ...;  [num] [color], ...; ...
-        // We don't need to parse the game number - they are in ascending order
-        let (_, results) = line.split_once(':').unwrap();
-        let results = results
-            .split(';')
-            .map(|res_line| {
-                let mut result: ShowResult = Default::default();
-                for entry in res_line.split(',') {
-                    // Should have something of the form {num} {color}
-                    let entry = entry.trim();
-                    let (num, color) = entry.split_once(' ').unwrap();
-                    let value = num.parse::<u16>().unwrap();
-                    match color {
-                        "red" => result.red = value,
-                        "blue" => result.blue = value,
-                        "green" => result.green = value,
-                        _ => panic!("Invalid color"),
-                    }
-                }
-                result
-            })
-            .collect();
-
-        Game {
-            number: index,
-            results,
-        }
+        let (label, draws) = line.labelled()?;
+        let [number] = label.captures("Game {}")?;
+        let draws = draws
+            .split(";")
+            .map(Cubes::parse)
+            .collect::<ParseResult<_>>()?;
+
+        Ok(Game {
+            number: number.parse()?,
+            draws,
+        })
     }
 
-    // Get the power value of a game.
-    // The "power" is the product of the minumum possible cubes, which
-    // is the *largest* of each of the red, blue and green seen across
-    // each show in the game.
-    fn get_power(&self) -> u32 {
-        // Get the total for each game.
-        let min_red = self
-            .results
-            .iter()
-            .max_by_key(|&r| r.red)
-            .map_or(0, |r| r.red) as u32;
-        let min_blue = self
-            .results
-            .iter()
-            .max_by_key(|&r| r.blue)
-            .map_or(0, |r| r.blue) as u32;
-        let min_green = self
-            .results
-            .iter()
-            .max_by_key(|&r| r.green)
-            .map_or(0, |r| r.green) as u32;
+    // Every draw needs to fit in the bag.
+    fn possible_with(&self, bag: &Cubes) -> bool {
+        self.draws.iter().all(|draw| draw.fits_in(bag))
+    }
 
-        min_red * min_blue * min_green
+    // The fewest cubes the bag could have had, which is the *largest* of each colour seen
+    // across the draws.
+    fn minimal_bag(&self) -> Cubes {
+        self.draws.iter().fold(Cubes::default(), Cubes::max)
     }
 }
 
-pub fn run(input_path: String) {
+// Which games could have been played with this bag?
+fn possible_games<'a>(games: &'a [Game], bag: &'a Cubes) -> impl Iterator<Item = &'a Game> {
+    games.iter().filter(|game| game.possible_with(bag))
+}
+
+pub fn run(input_path: String) -> Result<(), String> {
     let input = std::fs::read_to_string(input_path).unwrap();
 
-    let games = input
+    let games = Span::new(&input)
         .lines()
-        .enumerate()
-        // Use the array index + 1 as the game index, which is a valid assumption.
-        .map(|(idx, line)| Game::from_string(idx + 1, line));
-
-    // Count games where *every* result in the game has at most 12 red, 13 green and 14 blue
-    let part1 = games
-        .clone() // Needed to reuse for part2.
-        .filter(|game| {
-            game.results
-                .iter()
-                .all(|res| res.red <= 12 && res.green <= 13 && res.blue <= 14)
-        })
+        .map(Game::parse)
+        .collect::<ParseResult<Vec<_>>>()?;
+
+    let bag_text = opts::value("bag").unwrap_or(PART1_BAG.to_string());
+    let bag = Cubes::parse(Span::new(&bag_text)).map_err(|e| format!("Bad bag: {e}"))?;
+    if bag_text != PART1_BAG {
+        println!("Checking against bag: {}", bag);
+    }
+
+    // Add up the numbers of the games that could have come from the bag.
+    let part1 = possible_games(&games, &bag)
         .map(|game| game.number)
         .sum::<usize>();
 
-    // Sum the powers.
-    let part2 = games.map(|g| g.get_power()).sum::<u32>();
+    // The "power" is the product of the minimum possible cubes.
+    let part2 = games
+        .iter()
+        .map(|g| g.minimal_bag().power(&POWER_COLOURS))
+        .sum::<u64>();
 
     println!("Part 1: {}", part1);
     println!("Part 2: {}", part2);
+    Ok(())
 }
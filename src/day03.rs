@@ -1,149 +1,403 @@
-// Felt straightforward.  Parse out the number and symbol positions
-// into HashSets for fast lookup: then we can simply check neighbours of the numbers
-// as needed.  Part 2 wasn't that much extra work pleasingly.
-// The use of  1-indexed coordinates allows including the row/column "outside" the
-// grid as neighbours and not having to edge case or worry about bounds checks.
-// Since all we do with neighbour coord values is check if they are in the symbols list
-// this as fine.
+// Felt straightforward.  Parse out the number and symbol positions, then we can simply
+// check neighbours as needed.  Part 2 wasn't that much extra work pleasingly.
 // Some small things I missed first time:
 // - Remembering to handle numbers the finish on the end of a row
 // - Using `..=` for inclusive ranges!
 //
-// There's again plenty of perf improvements at a complexity cost - for example:
-// - We could be using a HashMap for the number entries keyed off their position,
-// so we can avoid checking numbers miles away for neighbouring gears.
-// - We could keep track of neighbours/gears as we parse the input, rather than
-// calculating them later.
-use std::collections::HashSet;
+// The first version kept the numbers and symbols in HashSets, and used 1-indexed
+// coordinates so the row/column "outside" the grid could be a neighbour without any bounds
+// checks.  Finding gears then meant scanning every number for every asterisk.
+// Now the schematic keeps a grid of cells, where each digit knows which number it's part
+// of.  So each symbol can just look at its 8 neighbouring cells to find its numbers, and
+// coordinates are signed so stepping off the edge is just a failed lookup.
+//
+// A "gear" is any symbol with exactly so many numbers next to it - an asterisk with two by
+// default, or pass eg `--gear=#3` for hashes with three.
+//
+// `--render=ansi` reprints the schematic in colour to show what counted: part numbers in
+// green, other numbers dimmed and gears in red, followed by a list of every symbol and its
+// numbers.  `--render=html` does the same as a web page.
+
+use std::collections::HashMap;
+
+use crate::opts;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
 struct NumberEntry {
     value: u32,
-    col_start: usize,
-    col_end: usize,
-    row: usize,
+    col_start: i64,
+    col_end: i64,
+    row: i64,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 struct Coordinate {
-    col: usize,
-    row: usize,
-}
-
-impl NumberEntry {
-    // Get all "neighbours" of a number.
-    fn get_neighbor_coords(&self) -> impl Iterator<Item = Coordinate> {
-        // Row before.
-        std::iter::repeat(self.row - 1)
-            .zip((self.col_start - 1)..=(self.col_end + 1))
-            .chain(
-                // Row after
-                std::iter::repeat(self.row + 1)
-                    .zip((self.col_start - 1)..=(self.col_end + 1))
-                    .chain(
-                        // Left element
-                        std::iter::once((self.row, self.col_start - 1))
-                            .chain(std::iter::once((self.row, self.col_end + 1))),
-                    ),
-            )
-            .map(|(row, col)| Coordinate { col, row })
+    col: i64,
+    row: i64,
+}
+
+impl Coordinate {
+    // All 8 cells around this one (some of which may be off the grid).
+    fn neighbours(self) -> impl Iterator<Item = Coordinate> {
+        (-1..=1)
+            .flat_map(|dr| (-1..=1).map(move |dc| (dr, dc)))
+            .filter(|&d| d != (0, 0))
+            .map(move |(dr, dc)| Coordinate {
+                col: self.col + dc,
+                row: self.row + dr,
+            })
     }
 }
 
-pub fn run(input_path: String) {
-    let input = std::fs::read_to_string(input_path).unwrap();
-    // Position of all symbols for part 1.
-    let mut symbols = HashSet::new();
-    // Position of all asterisks for part 2 - we'll work out which are
-    // actually gears later.
-    let mut asterisks = HashSet::new();
-    // Positions and values of all numbers.
-    let mut numbers = HashSet::new();
-    // We'll be parsing characer-by-charcter, so need to keep track of
-    // where we are in the current number.
-    let mut current_number = None;
-
-    // Parse the input.
-    for (row, line) in input.lines().enumerate() {
-        for (col, c) in line.char_indices() {
-            match c {
-                c if c.is_ascii_digit() => match current_number.as_mut() {
-                    None => {
-                        // Start of a number.
-                        // Add 1 to indicies because we want 1-indexing.
-                        current_number = Some(NumberEntry {
-                            value: c.to_digit(10).unwrap(),
-                            row: row + 1,
-                            col_start: col + 1,
-                            col_end: col + 1,
-                        })
-                    }
-                    Some(number) => {
-                        // Another digit of the number.  Modify accordingly.
-                        number.value *= 10;
-                        number.value += c.to_digit(10).unwrap();
-                        number.col_end += 1
-                    }
-                },
-                c => {
-                    // Handle end of number
-                    if let Some(n) = current_number.take() {
-                        numbers.insert(n);
-                    }
-                    if c == '*' {
-                        // Potential gear. Add 1 to indices because we want 1-indexing.
-                        asterisks.insert(Coordinate {
-                            col: col + 1,
-                            row: row + 1,
-                        });
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Cell {
+    Empty,
+    // A digit of the number with this index.
+    Digit(usize),
+    Symbol(char),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Symbol {
+    symbol: char,
+    pos: Coordinate,
+    // Indices of the numbers next to it.
+    numbers: Vec<usize>,
+}
+
+struct Schematic {
+    cells: Vec<Vec<Cell>>,
+    numbers: Vec<NumberEntry>,
+    symbols: Vec<Symbol>,
+}
+
+impl Schematic {
+    fn parse(input: &str) -> Self {
+        let mut cells = vec![];
+        let mut numbers: Vec<NumberEntry> = vec![];
+        let mut symbols = vec![];
+        for (row, line) in input.lines().enumerate() {
+            let row = row as i64;
+            // We're going character-by-charcter, so need to keep track of whether we're in
+            // the middle of a number.
+            let mut in_number = false;
+            let mut row_cells = vec![];
+            for (col, c) in line.chars().enumerate() {
+                let col = col as i64;
+                let cell = match c {
+                    c if c.is_ascii_digit() => {
+                        let digit = c.to_digit(10).unwrap();
+                        if in_number {
+                            // Another digit of the number.  Modify accordingly.
+                            let number = numbers.last_mut().unwrap();
+                            number.value = number.value * 10 + digit;
+                            number.col_end = col;
+                        } else {
+                            numbers.push(NumberEntry {
+                                value: digit,
+                                col_start: col,
+                                col_end: col,
+                                row,
+                            });
+                        }
+                        in_number = true;
+                        Cell::Digit(numbers.len() - 1)
                     }
-                    if c != '.' {
-                        // Symbol. Add 1 to indices because we want 1-indexing.
-                        symbols.insert(Coordinate {
-                            col: col + 1,
-                            row: row + 1,
+                    '.' => Cell::Empty,
+                    c => {
+                        symbols.push(Symbol {
+                            symbol: c,
+                            pos: Coordinate { col, row },
+                            numbers: vec![],
                         });
+                        Cell::Symbol(c)
                     }
+                };
+                if !c.is_ascii_digit() {
+                    in_number = false;
                 }
+                row_cells.push(cell);
             }
+            cells.push(row_cells);
         }
-        // If the row finished with a number, save it off now!
-        if let Some(n) = current_number.take() {
-            numbers.insert(n);
+
+        let mut schematic = Schematic {
+            cells,
+            numbers,
+            symbols,
+        };
+        // Now everything's in place, find each symbol's neighbours.
+        for idx in 0..schematic.symbols.len() {
+            let mut adjacent = schematic.symbols[idx]
+                .pos
+                .neighbours()
+                .filter_map(|c| schematic.number_at(c))
+                .collect::<Vec<_>>();
+            // A number can be next to a symbol in more than one place.
+            adjacent.sort();
+            adjacent.dedup();
+            schematic.symbols[idx].numbers = adjacent;
         }
+        schematic
     }
 
-    // For part 1: Find all the numbers that have at least 1 symbol in their neighbours.
-    let part_numbers = numbers
-        .iter()
-        .filter(|&number| {
-            number
-                .get_neighbor_coords()
-                .any(|neighbor| symbols.contains(&neighbor))
-        })
-        .map(|number| number.value);
-
-    println!("Part 1: {}", part_numbers.sum::<u32>());
-
-    // Find the gears.  These are asterisks who have exactly two neighbouring numbers.
-    let gear_ratios = asterisks.iter().filter_map(|this| {
-        // Look for neighbouring numbers to work out if this is a gear, and if so what
-        // the ratio is.
-        // Note that we are getting each number's neighbours and seeing  if they
-        // contain our gear, rather than working out neighbours of the gear and
-        // seeing which numbers cross them.  This is only because we already had the
-        // first method written for part1.
-        let neighbors = numbers
+    fn get(&self, pos: Coordinate) -> Option<Cell> {
+        let row = usize::try_from(pos.row).ok()?;
+        let col = usize::try_from(pos.col).ok()?;
+        self.cells.get(row)?.get(col).copied()
+    }
+
+    // Which number, if any, is at this position.
+    fn number_at(&self, pos: Coordinate) -> Option<usize> {
+        match self.get(pos)? {
+            Cell::Digit(idx) => Some(idx),
+            _ => None,
+        }
+    }
+
+    // Whether each number is next to at least one symbol.
+    fn part_numbers(&self) -> Vec<bool> {
+        let mut is_part = vec![false; self.numbers.len()];
+        for &idx in self.symbols.iter().flat_map(|s| &s.numbers) {
+            is_part[idx] = true;
+        }
+        is_part
+    }
+
+    // The symbols that count as gears under the rule.
+    fn gears<'a>(&'a self, rule: &'a GearRule) -> impl Iterator<Item = &'a Symbol> {
+        self.symbols
             .iter()
-            .filter(|n| n.get_neighbor_coords().any(|c| c == *this));
-        if neighbors.clone().count() == 2 {
-            // This is a gear - asterisk with 2 numeric neighbours - so multiply the
-            // values to get the ratio
-            Some(neighbors.map(|neigh| neigh.value as u64).product::<u64>())
+            .filter(|s| s.symbol == rule.symbol && s.numbers.len() == rule.neighbours)
+    }
+
+    // Multiply the values of a gear's numbers.
+    fn ratio(&self, gear: &Symbol) -> u64 {
+        gear.numbers
+            .iter()
+            .map(|&idx| self.numbers[idx].value as u64)
+            .product()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct GearRule {
+    symbol: char,
+    neighbours: usize,
+}
+
+impl GearRule {
+    // Symbol then count, like `*2`.
+    fn parse(text: &str) -> Option<Self> {
+        let mut chars = text.chars();
+        let symbol = chars.next()?;
+        let neighbours = chars.as_str().parse().ok()?;
+        Some(GearRule { symbol, neighbours })
+    }
+}
+
+impl Default for GearRule {
+    fn default() -> Self {
+        GearRule {
+            symbol: '*',
+            neighbours: 2,
+        }
+    }
+}
+
+// Ways to show the schematic with what counted highlighted, for when the answer's wrong.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Render {
+    Ansi,
+    Html,
+}
+
+// What a bit of the schematic turned out to be.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Highlight {
+    Part,
+    Unconnected,
+    Symbol,
+    Gear,
+}
+
+impl Render {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "ansi" => Some(Render::Ansi),
+            "html" => Some(Render::Html),
+            _ => None,
+        }
+    }
+
+    fn mark(self, text: &str, highlight: Highlight) -> String {
+        match self {
+            Render::Ansi => {
+                let code = match highlight {
+                    Highlight::Part => "1;32",
+                    Highlight::Unconnected => "2",
+                    Highlight::Symbol => "1;36",
+                    Highlight::Gear => "1;31",
+                };
+                format!("\x1b[{code}m{text}\x1b[0m")
+            }
+            Render::Html => {
+                let class = match highlight {
+                    Highlight::Part => "part",
+                    Highlight::Unconnected => "unconnected",
+                    Highlight::Symbol => "symbol",
+                    Highlight::Gear => "gear",
+                };
+                format!("<span class=\"{class}\">{}</span>", escape_html(text))
+            }
+        }
+    }
+
+    fn text(self, text: &str) -> String {
+        match self {
+            Render::Ansi => text.to_string(),
+            Render::Html => escape_html(text),
+        }
+    }
+}
+
+// Several of the symbols mean something in HTML.
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+const HTML_HEADER: &str = "<!DOCTYPE html>
+<html><head><meta charset=\"utf-8\"><title>Engine schematic</title><style>
+body { background: #111; color: #ccc; font-family: monospace; }
+.part { color: #4c4; font-weight: bold; }
+.unconnected { color: #555; }
+.symbol { color: #4cc; font-weight: bold; }
+.gear { color: #f44; font-weight: bold; }
+</style></head><body>";
+
+impl Schematic {
+    // Reprint the schematic, with part numbers highlighted, other numbers dimmed and gears
+    // marked (with their ratios at the end of the row).  Then list every symbol along with
+    // the numbers next to it.
+    fn render(&self, rule: &GearRule, render: Render) -> String {
+        let is_part = self.part_numbers();
+        let gears = self
+            .gears(rule)
+            .map(|g| (g.pos, self.ratio(g)))
+            .collect::<HashMap<_, _>>();
+        let mut out = String::new();
+        if render == Render::Html {
+            out.push_str(HTML_HEADER);
+            out.push_str("\n<pre>\n");
+        }
+
+        for (row, cells) in self.cells.iter().enumerate() {
+            let mut ratios = vec![];
+            for (col, &cell) in cells.iter().enumerate() {
+                let pos = Coordinate {
+                    col: col as i64,
+                    row: row as i64,
+                };
+                match cell {
+                    Cell::Empty => out.push('.'),
+                    Cell::Digit(idx) => {
+                        // Do the whole number in one go when we hit its first digit.
+                        let number = &self.numbers[idx];
+                        if number.col_start == pos.col {
+                            let width = (number.col_end - number.col_start + 1) as usize;
+                            let text = format!("{:0width$}", number.value);
+                            let highlight = if is_part[idx] {
+                                Highlight::Part
+                            } else {
+                                Highlight::Unconnected
+                            };
+                            out.push_str(&render.mark(&text, highlight));
+                        }
+                    }
+                    Cell::Symbol(c) => match gears.get(&pos) {
+                        Some(&ratio) => {
+                            ratios.push(ratio);
+                            out.push_str(&render.mark(&c.to_string(), Highlight::Gear));
+                        }
+                        None => out.push_str(&render.mark(&c.to_string(), Highlight::Symbol)),
+                    },
+                }
+            }
+            if !ratios.is_empty() {
+                let ratios = ratios.iter().map(|r| r.to_string()).collect::<Vec<_>>();
+                out.push_str(&render.text(&format!("  ratio {}", ratios.join(", "))));
+            }
+            out.push('\n');
+        }
+
+        if render == Render::Html {
+            out.push_str("</pre>\n<ul>\n");
         } else {
-            // Not a gear.
-            None
+            out.push('\n');
+        }
+        for symbol in &self.symbols {
+            let numbers = symbol
+                .numbers
+                .iter()
+                .map(|&idx| self.numbers[idx].value.to_string())
+                .collect::<Vec<_>>();
+            let mut line = format!(
+                "{} at row {}, column {}: {}",
+                symbol.symbol,
+                symbol.pos.row + 1,
+                symbol.pos.col + 1,
+                if numbers.is_empty() {
+                    "no numbers".to_string()
+                } else {
+                    numbers.join(", ")
+                }
+            );
+            if let Some(ratio) = gears.get(&symbol.pos) {
+                line.push_str(&format!(" - gear, ratio {ratio}"));
+            }
+            match render {
+                Render::Ansi => out.push_str(&line),
+                Render::Html => out.push_str(&format!("<li>{}</li>", escape_html(&line))),
+            }
+            out.push('\n');
+        }
+        if render == Render::Html {
+            out.push_str("</ul>\n</body></html>\n");
         }
-    });
-    println!("Part 2: {}", gear_ratios.sum::<u64>());
+        out
+    }
+}
+
+pub fn run(input_path: String) -> Result<(), String> {
+    let input = std::fs::read_to_string(input_path).unwrap();
+    let schematic = Schematic::parse(&input);
+    let rule = match opts::value("gear") {
+        Some(text) => GearRule::parse(&text).ok_or(format!("Bad gear rule {text}"))?,
+        None => GearRule::default(),
+    };
+    if let Some(name) = opts::value("render") {
+        let render = Render::parse(&name).ok_or(format!("Unknown rendering {name}"))?;
+        print!("{}", schematic.render(&rule, render));
+    }
+
+    // For part 1: Find all the numbers that have at least 1 symbol in their neighbours.
+    let part1 = schematic
+        .numbers
+        .iter()
+        .zip(schematic.part_numbers())
+        .filter(|(_, is_part)| *is_part)
+        .map(|(number, _)| number.value)
+        .sum::<u32>();
+
+    // Find the gears, and add up their ratios.
+    let part2 = schematic
+        .gears(&rule)
+        .map(|gear| schematic.ratio(gear))
+        .sum::<u64>();
+
+    println!("Part 1: {}", part1);
+    println!("Part 2: {}", part2);
+    Ok(())
 }
@@ -3,70 +3,167 @@
 // and fighting the borrow checker.
 // In the end, switching to tracking copies separately solved that.
 // Otherwise, not a lot of interest - a relatively straightforward day I think.
+//
+// We're promised no card wins copies past the end of the table, so by default that's an
+// error - pass `--past-end=clip` to just ignore those copies instead.  `--trace` shows how
+// many copies of each card there were and where they came from.  The cards don't have to be
+// listed in order, but each one can only be listed once.
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+
+use crate::opts;
+use crate::parse::{ParseResult, Span};
 
 // Parse the whitespace-separated set of numbers.
-fn parse_set(line: &str) -> HashSet<u8> {
-    line.split_whitespace()
-        .map(|s| s.parse().unwrap())
-        .collect()
+fn parse_set(line: Span) -> ParseResult<HashSet<u32>> {
+    Ok(line.numbers()?.into_iter().collect())
 }
 
 #[derive(Debug, Clone)]
 struct Card {
-    id: u8,
-    matches: u8,
+    id: u32,
+    matches: u32,
 }
 
 impl Card {
+    fn parse(line: Span) -> ParseResult<Self> {
+        let (label, numbers) = line.labelled()?;
+        // There can be several spaces before the number.
+        let [id] = label.captures("Card {}")?;
+        let (winners, this_card) = numbers.split_once(" | ")?;
+        let winners = parse_set(winners)?;
+        let this_card = parse_set(this_card)?;
+        Ok(Card {
+            id: id.trim().parse()?,
+            matches: winners.intersection(&this_card).count() as u32,
+        })
+    }
+
     // Calculate the score, which is 0 for no matches, 2^(n-1) for 1 or more.
     // (Don't think there's a single mathematical function to get that...)
-    fn part1_score(&self) -> u32 {
+    fn part1_score(&self) -> u64 {
         if self.matches == 0 {
             0
         } else {
-            2u32.pow(self.matches as u32 - 1)
+            2u64.pow(self.matches - 1)
         }
     }
 }
 
-pub fn run(input_path: String) {
-    let input = std::fs::read_to_string(input_path).unwrap();
-    let num_games = input.lines().count();
-
-    let cards = input.lines().enumerate().map(|(idx, line)| {
-        // Parsing.
-        let (_, numbers) = line.split_once(": ").unwrap();
-        let mut numbersets = numbers.split(" | ");
-        let winners = parse_set(numbersets.next().unwrap());
-        let this_card = parse_set(numbersets.next().unwrap());
-        let match_count = winners.intersection(&this_card).count() as u8;
-        Card {
-            // As in other days, rely on the Card number being line number + 1.
-            id: idx as u8 + 1,
-            matches: match_count,
+// Parse all the cards, in ID order (whatever order they're listed in).  Each ID can only
+// be used once.
+fn parse_cards(input: Span) -> ParseResult<Vec<Card>> {
+    let mut seen = HashSet::new();
+    let mut cards = vec![];
+    for line in input.lines() {
+        let card = Card::parse(line)?;
+        if !seen.insert(card.id) {
+            return Err(line.error(format!("duplicate card {}", card.id)));
+        }
+        cards.push(card);
+    }
+    cards.sort_by_key(|card| card.id);
+    Ok(cards)
+}
+
+// What to do when a card wins copies of cards that aren't there.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PastEnd {
+    Clip,
+    Error,
+}
+
+impl PastEnd {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "clip" => Some(PastEnd::Clip),
+            "error" => Some(PastEnd::Error),
+            _ => None,
+        }
+    }
+}
+
+// How many copies of each card we end up with (in the same order as the cards), and
+// optionally which cards they came from.
+struct Copies {
+    counts: Vec<u128>,
+    // For each card, (card ID, copies won from it).
+    sources: Option<Vec<Vec<(u32, u128)>>>,
+}
+
+// Copies only go to higher-numbered cards, so the cards must be in ID order.
+fn count_copies(cards: &[Card], past_end: PastEnd, trace: bool) -> Result<Copies, String> {
+    let index = cards
+        .iter()
+        .enumerate()
+        .map(|(idx, card)| (card.id, idx))
+        .collect::<HashMap<_, _>>();
+
+    // We start with 1 copy of each card.
+    let mut counts = vec![1; cards.len()];
+    let mut sources = trace.then(|| vec![vec![]; cards.len()]);
+    for (idx, card) in cards.iter().enumerate() {
+        let this_copies = counts[idx];
+        for offset in 1..=card.matches {
+            // For each copy of *this* card, add a copy to the next card up.  (Widened, as
+            // IDs can go right up to the top of a u32, and anything past that isn't there.)
+            let next_id = u64::from(card.id) + u64::from(offset);
+            let next = u32::try_from(next_id)
+                .ok()
+                .and_then(|next_id| index.get(&next_id));
+            let Some(&next) = next else {
+                match past_end {
+                    PastEnd::Clip => continue,
+                    PastEnd::Error => {
+                        return Err(format!(
+                            "Card {} wins a copy of card {next_id}, which doesn't exist",
+                            card.id
+                        ))
+                    }
+                }
+            };
+            counts[next] += this_copies;
+            if let Some(sources) = sources.as_mut() {
+                sources[next].push((card.id, this_copies));
+            }
         }
-    });
+    }
+    Ok(Copies { counts, sources })
+}
+
+pub fn run(input_path: String) -> Result<(), String> {
+    let input = std::fs::read_to_string(input_path).unwrap();
+    let past_end = match opts::value("past-end") {
+        Some(name) => PastEnd::parse(&name).ok_or(format!("Unknown past-end option {name}"))?,
+        None => PastEnd::Error,
+    };
+
+    let cards = parse_cards(Span::new(&input))?;
 
     // Part1 - just sum the scores of each card.
-    println!(
-        "Part 1: {}",
-        cards.clone().map(|card| card.part1_score()).sum::<u32>()
-    );
+    let part1 = cards.iter().map(|card| card.part1_score()).sum::<u64>();
 
     // For part 2, keep a count of copies of each card.
-    // Cards are 1-indexed and this list is 0-indexed!
-    // We start with 1 copy of each card.
-    let mut card_copies = vec![1; num_games];
-    for card in cards {
-        let this_copies = card_copies[card.id as usize - 1];
-        for i in 1..=card.matches {
-            // For each copy of *this* card, add a copy to the next card up.
-            let next_id = (i + card.id) as usize;
-            card_copies[next_id - 1] += this_copies;
+    let copies = count_copies(&cards, past_end, opts::flag("trace"))?;
+    if let Some(sources) = &copies.sources {
+        for ((card, count), from) in cards.iter().zip(&copies.counts).zip(sources) {
+            let from = from
+                .iter()
+                .map(|(id, n)| format!("{n} from card {id}"))
+                .collect::<Vec<_>>();
+            println!(
+                "Card {}: {} matches, {} copies (1 original{}{})",
+                card.id,
+                card.matches,
+                count,
+                if from.is_empty() { "" } else { ", " },
+                from.join(", ")
+            );
         }
     }
+    let part2 = copies.counts.iter().sum::<u128>();
 
-    println!("Part 2: {}", card_copies.iter().sum::<u32>());
+    println!("Part 1: {}", part1);
+    println!("Part 2: {}", part2);
+    Ok(())
 }
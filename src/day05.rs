@@ -3,147 +3,400 @@
 // Originally solved with brute-force (see commit history) - runs in about 3 minutes which is really too slow.
 // Took ages to get this right because I had a mental block about how overlapping works, but in the end
 // I quite like this.
+//
+// Each table is really a piecewise function: split the numbers into ranges, and shift each
+// range by some offset (zero for the gaps between the rules).  And composing two of those
+// just gives another, with the pieces split wherever either one changes.  So fold all
+// seven tables into one seed-to-location map up front, and then any seed (or seed range) is
+// just a binary search away from its location.  Pass `--show-map` to print the composed map.
+//
+// Going backwards is handy for checking answers: `--seeds-for=46` (or a range, `40..50`)
+// shows which seeds end up at those locations, and `--trace=79,14` shows every category a
+// seed goes through on the way.
+
+use std::fmt;
+
+use crate::{
+    interval::{Interval, RangeSet},
+    opts,
+    parse::{ParseResult, Span},
+};
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 struct MapBucket {
-    src_range: (u64, u64),
-    dest_range: (u64, u64),
+    src_range: Interval,
+    dest_range: Interval,
 }
 
-const DOUBLE_BLANK_LINE: &str = "\n\n";
-
 // Parsing.
 impl MapBucket {
-    fn from_str(input: &str) -> Self {
-        let mut words = input.split(' ');
-        let dest_start = words.next().unwrap().parse().unwrap();
-        let src_start = words.next().unwrap().parse().unwrap();
-        let size: u64 = words.next().unwrap().parse().unwrap();
-        MapBucket {
-            src_range: (src_start, src_start + size),
-            dest_range: (dest_start, dest_start + size),
-        }
+    fn from_str(input: Span) -> ParseResult<Self> {
+        let [dest_start, src_start, size] = input.captures("{} {} {}")?;
+        let size = size.parse()?;
+        Ok(MapBucket {
+            src_range: Interval::with_len(src_start.parse()?, size),
+            dest_range: Interval::with_len(dest_start.parse()?, size),
+        })
     }
 
-    fn apply(&self, input: u64) -> u64 {
-        input - self.src_range.0 + self.dest_range.0
+    // How far this bucket moves its inputs.
+    fn offset(&self) -> i64 {
+        self.dest_range.start - self.src_range.start
     }
 }
 
-fn is_in_range(n: u64, range: (u64, u64)) -> bool {
-    n >= range.0 && n < range.1
+// Every number a map could be asked about.
+const DOMAIN: Interval = Interval {
+    start: i64::MIN,
+    end: i64::MAX,
+};
+
+// Part of a piecewise map: everything in `src` moves by `offset`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Piece {
+    src: Interval,
+    offset: i64,
 }
 
-// Apply the mapping, to get all the ranges that this range maps to in the destination.
-fn apply_mapping_range(
-    input_ranges: impl IntoIterator<Item = (u64, u64)>,
-    mapping: &[MapBucket],
-) -> Vec<(u64, u64)> {
-    input_ranges
-        .into_iter()
-        .flat_map(|(start, end)| {
-            assert!(start <= end);
-            // Find all the mappings that overlap this range.
-            let overlap_buckets = mapping
-                .iter()
-                // Any overlap if the end is after the mapping start and the start before the mapping end.
-                // Had to draw this out to convince myself!
-                .filter(|bucket| end >= bucket.src_range.0 && start < bucket.src_range.1)
-                .collect::<Vec<_>>();
+impl Piece {
+    fn dest(&self) -> Interval {
+        self.src.translate(self.offset)
+    }
+}
 
-            // No overlap so just return the input.
-            if overlap_buckets.is_empty() {
-                return vec![(start, end)];
-            }
+// The pieces are sorted and cover the whole domain with no gaps, so the piece for any
+// number can be found with a binary search.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PiecewiseMap {
+    pieces: Vec<Piece>,
+}
 
-            let mut ranges = vec![];
-            let mut prev_bucket: Option<&MapBucket> = None;
+impl PiecewiseMap {
+    fn identity() -> Self {
+        PiecewiseMap {
+            pieces: vec![Piece {
+                src: DOMAIN,
+                offset: 0,
+            }],
+        }
+    }
 
-            // Add any part of the range before and after the overlapping buckets.
-            if let Some(b) = overlap_buckets.first() {
-                if start < b.src_range.0 {
-                    ranges.push((start, b.src_range.0))
-                }
+    // One of the almanac's tables, with the gaps between the rules filled in.
+    fn from_buckets(buckets: &[MapBucket]) -> Result<Self, String> {
+        let mut buckets = buckets.to_vec();
+        buckets.sort_by_key(|b| b.src_range.start);
+        let mut pieces = vec![];
+        let mut next = DOMAIN.start;
+        for bucket in buckets {
+            if bucket.src_range.start < next {
+                return Err(format!("mapping for {} overlaps another", bucket.src_range));
+            }
+            if bucket.src_range.start > next {
+                pieces.push(Piece {
+                    src: Interval::new(next, bucket.src_range.start),
+                    offset: 0,
+                });
             }
-            if let Some(b) = overlap_buckets.last() {
-                if end > b.src_range.1 {
-                    ranges.push((b.src_range.1, end))
+            pieces.push(Piece {
+                src: bucket.src_range,
+                offset: bucket.offset(),
+            });
+            next = bucket.src_range.end;
+        }
+        pieces.push(Piece {
+            src: Interval::new(next, DOMAIN.end),
+            offset: 0,
+        });
+        Ok(Self::merged(pieces))
+    }
+
+    // Tidy up a sorted list of pieces: drop empty ones, and join neighbours that move by the
+    // same amount.
+    fn merged(pieces: Vec<Piece>) -> Self {
+        let mut merged: Vec<Piece> = Vec::with_capacity(pieces.len());
+        for piece in pieces.into_iter().filter(|p| !p.src.is_empty()) {
+            match merged.last_mut() {
+                Some(last) if last.offset == piece.offset && last.src.end == piece.src.start => {
+                    last.src.end = piece.src.end
                 }
+                _ => merged.push(piece),
             }
-            for bucket in &overlap_buckets {
-                if let Some(prev) = prev_bucket.take() {
-                    // Push the source gap between buckets, if there is a gap
-                    if prev.src_range.1 < bucket.src_range.0 {
-                        ranges.push((prev.src_range.1, bucket.src_range.0))
-                    }
+        }
+        PiecewiseMap { pieces: merged }
+    }
+
+    // The pieces that overlap a range, found with a binary search for the first.
+    fn pieces_over(&self, range: Interval) -> impl Iterator<Item = &Piece> + '_ {
+        let first = self.pieces.partition_point(|p| p.src.end <= range.start);
+        self.pieces[first..]
+            .iter()
+            .take_while(move |p| p.src.start < range.end)
+    }
+
+    fn map(&self, n: i64) -> i64 {
+        let piece = self.pieces_over(Interval::with_len(n, 1)).next().unwrap();
+        n + piece.offset
+    }
+
+    fn map_range(&self, range: Interval) -> RangeSet {
+        self.pieces_over(range)
+            .filter_map(|p| p.src.intersect(&range).map(|i| i.translate(p.offset)))
+            .collect()
+    }
+
+    fn map_ranges(&self, ranges: &RangeSet) -> RangeSet {
+        ranges
+            .iter()
+            .fold(RangeSet::new(), |acc, &r| acc.union(&self.map_range(r)))
+    }
+
+    // The map that does this one and then `next`.
+    // Each of our pieces lands on a range, which gets split up by the pieces of `next` it
+    // covers.  Since our pieces are in order and each just shifts its range, the results
+    // come out in order too.
+    fn then(&self, next: &PiecewiseMap) -> PiecewiseMap {
+        let mut pieces = vec![];
+        for piece in &self.pieces {
+            let dest = piece.dest();
+            for other in next.pieces_over(dest) {
+                if let Some(overlap) = other.src.intersect(&dest) {
+                    pieces.push(Piece {
+                        src: overlap.translate(-piece.offset),
+                        offset: piece.offset + other.offset,
+                    });
                 }
-                prev_bucket = Some(bucket);
-                match (
-                    is_in_range(start, bucket.src_range),
-                    is_in_range(end, bucket.src_range),
-                ) {
-                    // All in one bucket.  Only include the mapped range.
-                    (true, true) => ranges.push((bucket.apply(start), bucket.apply(end))),
-                    // Must be first bucket.  Just push the appropriate mapped section.
-                    (true, false) => ranges.push((bucket.apply(start), bucket.dest_range.1)),
-                    // Must be last bucket.  Just push the appropriate mapped section.
-                    (false, true) => ranges.push((bucket.dest_range.0, bucket.apply(end))),
-                    // Neither the start nor the end are in this bucket but it overlaps.
-                    // Push the whole destination range
-                    (false, false) => ranges.push(bucket.dest_range),
-                };
             }
-            ranges
-        })
-        .collect()
+        }
+        Self::merged(pieces)
+    }
+}
+
+impl fmt::Display for PiecewiseMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The ends of the domain are really just "everything else".
+        let bound = |n: i64| match n {
+            i64::MIN => "-inf".to_string(),
+            i64::MAX => "inf".to_string(),
+            n => n.to_string(),
+        };
+        for piece in &self.pieces {
+            let dest = piece.dest();
+            writeln!(
+                f,
+                "[{}, {}) -> [{}, {}) ({:+})",
+                bound(piece.src.start),
+                bound(piece.src.end),
+                bound(dest.start),
+                bound(dest.end),
+                piece.offset
+            )?;
+        }
+        Ok(())
+    }
 }
 
-// For each seed, work out its final location, and then get the minimum of those.
-fn solve(seeds: impl Iterator<Item = (u64, u64)>, mappings: &[Vec<MapBucket>]) -> u64 {
-    let final_ranges = seeds
-        .flat_map(|seed_range| {
-            // Run the mappings in order.
-            mappings.iter().fold(vec![seed_range], |input, mapping| {
-                apply_mapping_range(input, mapping)
+// Going backwards through a map: which numbers could have ended up in a range?
+// Unlike going forwards, that isn't a function - two ranges can land on the same place, and
+// some places are never landed on - so the pieces here can overlap or leave gaps.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct InverseMap {
+    // Sorted by start.  Each piece's `src` is a destination range of the original map.
+    pieces: Vec<Piece>,
+}
+
+impl InverseMap {
+    fn new(map: &PiecewiseMap) -> Self {
+        let mut pieces = map
+            .pieces
+            .iter()
+            .map(|p| Piece {
+                src: p.dest(),
+                offset: -p.offset,
             })
+            .collect::<Vec<_>>();
+        pieces.sort_by_key(|p| p.src.start);
+        InverseMap { pieces }
+    }
+
+    fn map_range(&self, range: Interval) -> RangeSet {
+        self.pieces
+            .iter()
+            .take_while(|p| p.src.start < range.end)
+            .filter_map(|p| p.src.intersect(&range).map(|i| i.translate(p.offset)))
+            .collect()
+    }
+
+    fn map_ranges(&self, ranges: &RangeSet) -> RangeSet {
+        ranges
+            .iter()
+            .fold(RangeSet::new(), |acc, &r| acc.union(&self.map_range(r)))
+    }
+}
+
+// One of the almanac's tables, and the categories it maps between.
+struct Stage {
+    from: String,
+    to: String,
+    map: PiecewiseMap,
+}
+
+impl Stage {
+    fn parse(section: Span) -> ParseResult<Self> {
+        let mut lines = section.lines();
+        let header = lines.next().ok_or_else(|| section.error("missing map"))?;
+        let [from, to] = header.captures("{}-to-{} map:")?;
+        // Each other line is a single mapping rule.
+        let buckets = lines
+            .map(MapBucket::from_str)
+            .collect::<ParseResult<Vec<_>>>()?;
+        Ok(Stage {
+            from: from.as_str().to_string(),
+            to: to.as_str().to_string(),
+            map: PiecewiseMap::from_buckets(&buckets).map_err(|msg| section.error(msg))?,
         })
-        .collect::<Vec<_>>();
-    final_ranges.into_iter().map(|(a, _)| a).min().unwrap()
+    }
 }
 
-pub fn run(input_path: String) {
-    let input = std::fs::read_to_string(input_path).unwrap();
+struct Almanac {
+    seeds: Vec<i64>,
+    // Each table, in order.
+    stages: Vec<Stage>,
+    // All of them in one go.
+    composed: PiecewiseMap,
+}
 
-    let (seed_str, map_str) = input.split_once(DOUBLE_BLANK_LINE).unwrap();
-    let (_, seed_part) = seed_str.split_once(": ").unwrap();
-    // Parse the initial seed numbers.
-    let seeds = seed_part
-        .trim()
-        .split(' ')
-        .map(|s| s.parse::<u64>().unwrap())
-        .collect::<Vec<_>>();
-
-    let mappings = map_str
-        .split(DOUBLE_BLANK_LINE)
-        .map(|section| {
-            // Map each mapping table
-            let mut table = section
-                .lines()
-                // Skip the header, it doesn't contain anything interesting.
-                .skip(1)
-                // Each line is a single mapping rule.
-                .map(MapBucket::from_str)
-                .collect::<Vec<_>>();
-            table.sort_by_key(|map| map.src_range.0);
-            table
+impl Almanac {
+    fn parse(input: Span) -> ParseResult<Self> {
+        let mut sections = input.sections();
+
+        // Parse the initial seed numbers.
+        let seed_str = sections
+            .next()
+            .ok_or_else(|| input.error("missing seeds"))?;
+        let (_, seed_part) = seed_str.labelled()?;
+        let seeds = seed_part.numbers::<i64>()?;
+
+        let mut stages: Vec<Stage> = vec![];
+        for section in sections {
+            let stage = Stage::parse(section)?;
+            // Each map should carry on from where the last one got to.
+            let expected = stages.last().map_or("seed", |s| &s.to);
+            if stage.from != expected {
+                return Err(section.error(format!(
+                    "expected a map from {expected}, not {}",
+                    stage.from
+                )));
+            }
+            stages.push(stage);
+        }
+        let composed = stages
+            .iter()
+            .fold(PiecewiseMap::identity(), |acc, stage| acc.then(&stage.map));
+
+        Ok(Almanac {
+            seeds,
+            stages,
+            composed,
         })
-        .collect::<Vec<_>>();
+    }
+
+    fn location(&self, seed: i64) -> i64 {
+        self.composed.map(seed)
+    }
+
+    fn locations(&self, seeds: &RangeSet) -> RangeSet {
+        let locations = self.composed.map_ranges(seeds);
+        // Should be the same as going through the tables one at a time.
+        debug_assert_eq!(
+            locations,
+            self.stages
+                .iter()
+                .fold(seeds.clone(), |acc, stage| stage.map.map_ranges(&acc))
+        );
+        locations
+    }
 
-    // Treat each seed a a single-element range, to reuse the part 2 code.
-    let part1 = solve(seeds.clone().into_iter().map(|s| (s, s)), &mappings);
+    // All the seeds that end up somewhere in `locations`.
+    fn seeds_for(&self, locations: &RangeSet) -> RangeSet {
+        let seeds = InverseMap::new(&self.composed).map_ranges(locations);
+        debug_assert_eq!(
+            seeds,
+            self.stages
+                .iter()
+                .rev()
+                .fold(locations.clone(), |acc, stage| {
+                    InverseMap::new(&stage.map).map_ranges(&acc)
+                })
+        );
+        seeds
+    }
+
+    // The category and value at each step of the way from a seed to its location.
+    fn trace(&self, seed: i64) -> Vec<(&str, i64)> {
+        let mut steps = vec![("seed", seed)];
+        let mut value = seed;
+        for stage in &self.stages {
+            value = stage.map.map(value);
+            steps.push((&stage.to, value));
+        }
+        steps
+    }
+}
+
+// A number, or a half-open range like `40..50`.
+fn parse_range(text: &str) -> Option<Interval> {
+    match text.split_once("..") {
+        Some((start, end)) => {
+            let (start, end) = (start.parse().ok()?, end.parse().ok()?);
+            (start <= end).then(|| Interval::new(start, end))
+        }
+        None => Some(Interval::with_len(text.parse().ok()?, 1)),
+    }
+}
+
+pub fn run(input_path: String) -> Result<(), String> {
+    let input = std::fs::read_to_string(input_path).unwrap();
+    let almanac = Almanac::parse(Span::new(&input))?;
+    if opts::flag("show-map") {
+        print!("{}", almanac.composed);
+    }
+    if let Some(seeds) = opts::value("trace") {
+        for seed in seeds.split(',') {
+            let seed = seed.parse().map_err(|_| format!("Bad seed {seed}"))?;
+            let steps = almanac
+                .trace(seed)
+                .iter()
+                .map(|(category, value)| format!("{category} {value}"))
+                .collect::<Vec<_>>();
+            println!("{}", steps.join(", "));
+        }
+    }
+
+    let part1 = almanac
+        .seeds
+        .iter()
+        .map(|&seed| almanac.location(seed))
+        .min()
+        .unwrap();
     println!("Part 1: {}", part1);
 
-    let seed_ranges = seeds.chunks(2).map(|chunk| (chunk[0], chunk[0] + chunk[1]));
-    let part2 = solve(seed_ranges, &mappings);
+    // Now the seeds are ranges, so the answer is the lowest location any of them reach.
+    let seed_ranges = almanac
+        .seeds
+        .chunks(2)
+        .map(|chunk| Interval::with_len(chunk[0], chunk[1]))
+        .collect();
+    let part2 = almanac.locations(&seed_ranges).min().unwrap();
     println!("Part 2: {}", part2);
+
+    if let Some(text) = opts::value("seeds-for") {
+        let locations = parse_range(&text).ok_or(format!("Bad location range {text}"))?;
+        let seeds = almanac.seeds_for(&locations.into());
+        println!("Seeds for locations {}: {}", locations, seeds);
+        println!(
+            "Of which in part 2's ranges: {}",
+            seeds.intersection(&seed_ranges)
+        );
+    }
+    Ok(())
 }
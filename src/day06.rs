@@ -8,36 +8,61 @@
 // quadratic equation formula.)
 // My only slowdown was an off by one in my inequality, and then forgetting to use u64s and
 // hitting wrapping for part 2.
+//
+// It now does use the quadratic formula, but with an exact integer square root rather than
+// floats - which get it wrong when a root lands exactly on the record.  It works for any
+// integer type: u128 is plenty for the real input, but part 2-style races can have 30+
+// digit times, and so records (and squares) twice that long, so the races are read as big
+// integers.  The linear scan is kept for the tests to check small races against.
+
+use num::{integer::Roots, BigUint, Integer};
+
+use crate::parse::{ParseError, ParseResult, Span};
 
 // Calculate the disatnce for a given charge time.
 // You travel (charge_time) millimeters for (total - charge) milliseconds.
-fn calculate_distance(total_time: u64, charge_time: u64) -> u64 {
+#[cfg(test)]
+fn calculate_distance(total_time: u128, charge_time: u128) -> u128 {
     (total_time - charge_time) * charge_time
 }
 
 // Parse out the numbers
-fn parse_line_part1(line: &str) -> impl Iterator<Item = u64> + '_ {
-    let (_, numbers) = line.split_once(':').unwrap();
-    numbers
-        .split_whitespace()
-        .map(|s| s.parse::<u64>().unwrap())
+fn parse_line_part1(line: Span) -> ParseResult<Vec<BigUint>> {
+    let (_, numbers) = line.labelled()?;
+    numbers.numbers()
 }
 
 // Parse out all the numbers into a single number.
-fn parse_line_pt2(line: &str) -> u64 {
-    let (_, numbers) = line.split_once(':').unwrap();
+fn parse_line_pt2(line: Span) -> ParseResult<BigUint> {
+    let (_, numbers) = line.labelled()?;
     let value = numbers
+        .as_str()
         .chars()
         .filter(|c| c.is_ascii_digit())
         .collect::<String>();
-    value.parse::<u64>().unwrap()
+    value
+        .parse::<BigUint>()
+        .map_err(|e| numbers.error(format!("invalid number '{value}': {e}")))
 }
 
-// Count how many options would beat the target.
-fn find_race_winning_options(time: u64, target_distance: u64) -> u64 {
-    let min_charge = (0..time)
-        .find(|&charge_time| calculate_distance(time, charge_time) > target_distance)
-        .unwrap();
+// Get the time and distance lines.
+fn get_lines(input: Span) -> ParseResult<(Span, Span)> {
+    let mut lines = input.lines();
+    let times = lines.next().ok_or_else(|| input.error("missing times"))?;
+    let distances = lines
+        .next()
+        .ok_or_else(|| input.error("missing distances"))?;
+    Ok((times, distances))
+}
+
+// Count how many options would beat the target, the slow way.
+#[cfg(test)]
+fn scan_race_winning_options(time: u128, target_distance: u128) -> u128 {
+    let Some(min_charge) =
+        (0..=time / 2).find(|&charge_time| calculate_distance(time, charge_time) > target_distance)
+    else {
+        return 0;
+    };
     // The distribution is symmetric, so anything up to (total - min) will also win.
     // (Since the formula is i*(N-i)).
     let max_charge = time - min_charge;
@@ -45,25 +70,136 @@ fn find_race_winning_options(time: u64, target_distance: u64) -> u64 {
     1 + max_charge - min_charge
 }
 
-pub fn run(input_path: String) {
+// Count how many options would beat the target.
+// We want i*(N-i) > D, ie i^2 - Ni + D < 0, which is true strictly between the roots
+// (N +/- sqrt(N^2 - 4D)) / 2.  Rounding the square root down gets the first winner to
+// within one, so just nudge it until it's exactly right.
+fn find_race_winning_options<N: Integer + Roots + Clone>(time: &N, target_distance: &N) -> N {
+    let one = N::one();
+    let two = one.clone() + one.clone();
+    let wins = |charge: &N| charge.clone() * (time.clone() - charge.clone()) > *target_distance;
+
+    let square = time.clone() * time.clone();
+    let four_d = two.clone() * two.clone() * target_distance.clone();
+    if square <= four_d {
+        // At best we can only equal the record.
+        return N::zero();
+    }
+    let root = (square - four_d).sqrt();
+    let half = time.clone() / two.clone();
+    let mut min_charge = (time.clone() - root) / two;
+    while !wins(&min_charge) {
+        if min_charge >= half {
+            // The roots are too close together to have a whole number between them.
+            return N::zero();
+        }
+        min_charge = min_charge + one.clone();
+    }
+    while !min_charge.is_zero() && wins(&(min_charge.clone() - one.clone())) {
+        min_charge = min_charge - one.clone();
+    }
+    let max_charge = time.clone() - min_charge.clone();
+    one + max_charge - min_charge
+}
+
+pub fn run(input_path: String) -> Result<(), ParseError> {
     let input = std::fs::read_to_string(input_path).unwrap();
-    let mut lines = input.lines();
-    let times = lines.next().map(parse_line_part1).unwrap();
-    let distances = lines.next().map(parse_line_part1).unwrap();
-    let races = times.zip(distances);
+    let (time_line, distance_line) = get_lines(Span::new(&input))?;
+    let times = parse_line_part1(time_line)?;
+    let distances = parse_line_part1(distance_line)?;
+    let races = times.into_iter().zip(distances);
 
     // Part 1 wants product of all potential win counts.
     let part1 = races
-        .map(|(t, d)| find_race_winning_options(t, d))
-        .product::<u64>();
+        .map(|(t, d)| find_race_winning_options(&t, &d))
+        .product::<BigUint>();
 
     // Part 2 wants a single race win count.
-    let mut lines = input.lines();
-    let real_time = parse_line_pt2(lines.next().unwrap());
-    let real_distance = parse_line_pt2(lines.next().unwrap());
+    let real_time = parse_line_pt2(time_line)?;
+    let real_distance = parse_line_pt2(distance_line)?;
 
-    let part2 = find_race_winning_options(real_time, real_distance);
+    let part2 = find_race_winning_options(&real_time, &real_distance);
 
     println!("Part 1: {}", part1);
     println!("Part 2: {}", part2);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Small enough to check every race with the linear scan.
+    const SCAN_LIMIT: u128 = 300;
+
+    #[test]
+    fn matches_scan() {
+        for time in 0..=SCAN_LIMIT {
+            // Every distance we can actually reach is a record where the roots are whole
+            // numbers (the discriminant is a perfect square), so try those, either side of
+            // them, and records we can't beat at all.
+            let records = (0..=time / 2)
+                .map(|charge| calculate_distance(time, charge))
+                .flat_map(|d| [d.saturating_sub(1), d, d + 1])
+                .chain([time * time / 4 + 1, time * time]);
+            for record in records {
+                let scanned = scan_race_winning_options(time, record);
+                assert_eq!(
+                    find_race_winning_options(&time, &record),
+                    scanned,
+                    "time {time}, record {record}"
+                );
+                assert_eq!(
+                    find_race_winning_options(&BigUint::from(time), &BigUint::from(record)),
+                    BigUint::from(scanned),
+                    "time {time}, record {record}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn example() {
+        let input = "Time:      7  15   30\nDistance:  9  40  200\n";
+        let (time_line, distance_line) = get_lines(Span::new(input)).unwrap();
+        let times = parse_line_part1(time_line).unwrap();
+        let distances = parse_line_part1(distance_line).unwrap();
+        let counts = times
+            .iter()
+            .zip(&distances)
+            .map(|(t, d)| find_race_winning_options(t, d))
+            .collect::<Vec<_>>();
+        assert_eq!(counts, [4u32, 8, 9].map(BigUint::from));
+        let time = parse_line_pt2(time_line).unwrap();
+        let distance = parse_line_pt2(distance_line).unwrap();
+        assert_eq!(find_race_winning_options(&time, &distance), 71503u32.into());
+    }
+
+    #[test]
+    fn huge_race() {
+        // The record is exactly what charging for 987654321098765432109ms gets, so the
+        // winners are strictly between that and the same distance from the other end.
+        let input = "\
+Time:      1234567890 1234567890  1234567890 12
+Distance:  1219326311272671846462 2618499774900167417882334998427
+";
+        let (time_line, distance_line) = get_lines(Span::new(input)).unwrap();
+        let time = parse_line_pt2(time_line).unwrap();
+        let distance = parse_line_pt2(distance_line).unwrap();
+        assert_eq!(
+            time,
+            "12345678901234567890123456789012"
+                .parse::<BigUint>()
+                .unwrap()
+        );
+        let expected = "12345678899259259247925925924793"
+            .parse::<BigUint>()
+            .unwrap();
+        assert_eq!(find_race_winning_options(&time, &distance), expected);
+        // Any less, and both ends win too.
+        assert_eq!(
+            find_race_winning_options(&time, &(distance - 1u32)),
+            expected + 2u32
+        );
+    }
 }
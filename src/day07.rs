@@ -1,66 +1,22 @@
 // Day 7.
 // For part1 I got blocked for ages because I assumed regular poker rules.
-// Part 2 is  a bit ugly.  I don't like the duplication of the hands, but
-// it's readable enough IMO and does mean we only need one pass through the
-// data.
+// Part 2 first time round was a bit ugly - every hand carried two copies of its cards and
+// types, and the JJJJJ hand needed its own special case.
 //
-// Was a bit annoying to have to separately handle the edge case of JJJJJ in the input!
+// Now both parts are just different `Rules`: what order the cards go in, which are wild,
+// and how to break ties between hands of the same type.  Hands are parsed under a set of
+// rules and then sort by them.  `--wild=JQ` changes which cards are wild in part 2, and
+// `--tie-break=poker` breaks ties like real poker (biggest group first) in both parts.
+// `--report` lists each part 2 hand with how it got its type and where it ranked, and then
+// how many hands there were of each type.
+
 use std::cmp::Ordering;
 
-// Card values, in rank order.
-#[derive(Debug, Clone, Copy, PartialOrd, Ord, PartialEq, Eq)]
-enum Card {
-    Joker,
-    Two,
-    Three,
-    Four,
-    Five,
-    Six,
-    Seven,
-    Eight,
-    Nine,
-    Ten,
-    Jack,
-    Queen,
-    King,
-    Ace,
-}
-
-impl Card {
-    fn from_char(c: &char) -> Self {
-        match *c {
-            '2' => Card::Two,
-            '3' => Card::Three,
-            '4' => Card::Four,
-            '5' => Card::Five,
-            '6' => Card::Six,
-            '7' => Card::Seven,
-            '8' => Card::Eight,
-            '9' => Card::Nine,
-            'T' => Card::Ten,
-            'J' => Card::Jack,
-            'Q' => Card::Queen,
-            'K' => Card::King,
-            'A' => Card::Ace,
-            _ => panic!("invalid card"),
-        }
-    }
-}
-
-// We store the list of cards and types separately for
-// part1 and part2.  This is so "J" can have a different value in each
-// case.
-#[derive(Debug, Clone, PartialEq, Eq)]
-struct Hand {
-    cards_pt1: Vec<Card>,
-    cards_pt2: Vec<Card>,
-    hand_type_pt1: HandType,
-    hand_type_pt2: HandType,
-    bid: usize,
-}
+use crate::opts;
+use crate::parse::{ParseResult, Span};
 
 // Hand types, in rank order.
-#[derive(Debug, Clone, Copy, PartialOrd, Ord, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialOrd, Ord, PartialEq, Eq, Hash)]
 enum HandType {
     HighCard,
     Pair,
@@ -71,8 +27,85 @@ enum HandType {
     FiveKind,
 }
 
+const HAND_TYPES: [HandType; 7] = [
+    HandType::HighCard,
+    HandType::Pair,
+    HandType::TwoPair,
+    HandType::ThreeKind,
+    HandType::FullHouse,
+    HandType::FourKind,
+    HandType::FiveKind,
+];
+
+impl HandType {
+    fn name(self) -> &'static str {
+        match self {
+            HandType::HighCard => "high card",
+            HandType::Pair => "one pair",
+            HandType::TwoPair => "two pair",
+            HandType::ThreeKind => "three of a kind",
+            HandType::FullHouse => "full house",
+            HandType::FourKind => "four of a kind",
+            HandType::FiveKind => "five of a kind",
+        }
+    }
+}
+
+// How to order hands of the same type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TieBreak {
+    // Compare the cards one at a time in the order they were dealt.
+    Dealt,
+    // Compare the biggest group first (so the three in a full house beats the pair),
+    // then by card within groups of the same size.
+    Poker,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Rules {
+    // All the cards, lowest first.
+    order: Vec<char>,
+    // Wild cards count as whatever makes the best hand, but are the weakest card in a
+    // tie-break.
+    wild: Vec<char>,
+    tie_break: TieBreak,
+}
+
+const CARD_ORDER: &str = "23456789TJQKA";
+
+impl Rules {
+    fn part1() -> Self {
+        Rules {
+            order: CARD_ORDER.chars().collect(),
+            wild: vec![],
+            tie_break: TieBreak::Dealt,
+        }
+    }
+
+    // Jacks are now Jokers.
+    fn part2() -> Self {
+        Rules {
+            wild: vec!['J'],
+            ..Rules::part1()
+        }
+    }
+
+    fn is_wild(&self, card: char) -> bool {
+        self.wild.contains(&card)
+    }
+
+    // How strong a card is in a tie-break.  Wild cards are weaker than anything else.
+    fn strength(&self, card: char) -> u8 {
+        if self.is_wild(card) {
+            0
+        } else {
+            self.order.iter().position(|&c| c == card).unwrap() as u8 + 1
+        }
+    }
+}
+
 // Get a list of (card type, count) for a hand.
-fn get_card_counts(cards: &[Card]) -> Vec<(Card, u8)> {
+fn get_card_counts(cards: &[char]) -> Vec<(char, u8)> {
     cards.iter().fold(vec![], |mut set, this| {
         if let Some((_, ref mut count)) = set.iter_mut().find(|(c, _)| c == this) {
             *count += 1;
@@ -83,127 +116,192 @@ fn get_card_counts(cards: &[Card]) -> Vec<(Card, u8)> {
     })
 }
 
+// Work out the type from the two biggest groups of cards.
+fn get_hand_type(card_counts: &[(char, u8)]) -> HandType {
+    let mut counts = card_counts.iter().map(|&(_, n)| n).collect::<Vec<_>>();
+    counts.sort_by(|a, b| b.cmp(a));
+    match (counts[0], counts.get(1).copied().unwrap_or(0)) {
+        (5.., _) => HandType::FiveKind,
+        (4, _) => HandType::FourKind,
+        (3, 2..) => HandType::FullHouse,
+        (3, _) => HandType::ThreeKind,
+        (2, 2) => HandType::TwoPair,
+        (2, _) => HandType::Pair,
+        _ => HandType::HighCard,
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Hand {
+    cards: Vec<char>,
+    bid: u64,
+    // The type ignoring wild cards, and the type once they've been used.
+    raw_type: HandType,
+    hand_type: HandType,
+    // What the wild cards (if any) turned into.
+    wild_as: Option<char>,
+    // Card strengths in tie-break order.
+    tie_break: Vec<u8>,
+}
+
 impl Hand {
     // Parse the hand.
-    fn from_str(line: &str) -> Self {
-        let (card_str, bid_str) = line.split_once(' ').unwrap();
-        let bid = bid_str.parse().unwrap();
-        let cards_pt1 = card_str
+    fn parse(line: Span, rules: &Rules) -> ParseResult<Self> {
+        let [card_str, bid_str] = line.captures("{} {}")?;
+        let bid = bid_str.trim().parse()?;
+        let cards = card_str
             .chars()
-            .map(|c| Card::from_char(&c))
-            .collect::<Vec<_>>();
-        let card_counts_pt1 = get_card_counts(&cards_pt1);
-
-        // For part2, replace all the Jacks with Jokers.
-        let mut cards_pt2 = cards_pt1.clone();
-        for card in &mut cards_pt2 {
-            if *card == Card::Jack {
-                *card = Card::Joker
+            .map(|c| {
+                let card = c.as_str().chars().next().unwrap();
+                if rules.order.contains(&card) {
+                    Ok(card)
+                } else {
+                    Err(c.error(format!("invalid card '{card}'")))
+                }
+            })
+            .collect::<ParseResult<Vec<_>>>()?;
+
+        // The wild cards should all join the biggest group, or the best card if there's a
+        // tie.  (If they're all wild, they may as well all be the best card.)
+        let wild_count = cards.iter().filter(|&&c| rules.is_wild(c)).count() as u8;
+        let mut counts = get_card_counts(&cards);
+        let raw_type = get_hand_type(&counts);
+        counts.retain(|&(c, _)| !rules.is_wild(c));
+        let wild_as = (wild_count > 0).then(|| {
+            counts
+                .iter()
+                .max_by_key(|&&(c, n)| (n, rules.strength(c)))
+                .map_or(*rules.order.last().unwrap(), |&(c, _)| c)
+        });
+        if let Some(target) = wild_as {
+            match counts.iter_mut().find(|(c, _)| *c == target) {
+                Some((_, n)) => *n += wild_count,
+                None => counts.push((target, wild_count)),
             }
         }
-        let mut card_counts_pt2 = get_card_counts(&cards_pt2);
-
-        // Determine rank.
-        // Work out the most common card type in each hand.  Tie
-        let mut top_card_count = card_counts_pt2.iter().map(|card| card.1).max().unwrap();
-        let hand_type_pt1 = get_hand_type(&card_counts_pt1, top_card_count);
-        // For part 2, replace any J instances with whatever there are most of.
-        let hand_type_pt2 = if let Some(pos) = card_counts_pt2
-            .iter()
-            .position(|&(card, _)| card == Card::Joker)
-        {
-            let jokers = card_counts_pt2.remove(pos);
-            if jokers.1 == 5 {
-                // Special case the 5-Joker hand
-                HandType::FiveKind
-            } else {
-                {
-                    let (_, ref mut top_count) = card_counts_pt2
-                        .iter_mut()
-                        .max_by_key(|(_, count)| *count)
-                        .unwrap();
-                    *top_count += jokers.1;
-                    top_card_count = *top_count;
-                }
-                get_hand_type(&card_counts_pt2, top_card_count)
+        let hand_type = get_hand_type(&counts);
+
+        let tie_break = match rules.tie_break {
+            TieBreak::Dealt => cards.iter().map(|&c| rules.strength(c)).collect(),
+            TieBreak::Poker => {
+                counts.sort_by_key(|&(c, n)| (n, rules.strength(c)));
+                counts
+                    .iter()
+                    .rev()
+                    .flat_map(|&(c, n)| std::iter::repeat_n(rules.strength(c), n as usize))
+                    .collect()
             }
-        } else {
-            hand_type_pt1
         };
 
-        Hand {
-            cards_pt1,
-            cards_pt2,
+        Ok(Hand {
+            cards,
             bid,
-            hand_type_pt1,
-            hand_type_pt2,
-        }
+            raw_type,
+            hand_type,
+            wild_as,
+            tie_break,
+        })
     }
 }
 
-fn get_hand_type(card_counts: &[(Card, u8)], top_card_count: u8) -> HandType {
-    match card_counts.len() {
-        1 => HandType::FiveKind,
-        2 if top_card_count == 4 => HandType::FourKind,
-        2 if top_card_count == 3 => HandType::FullHouse,
-        3 if top_card_count == 3 => HandType::ThreeKind,
-        3 if top_card_count == 2 => HandType::TwoPair,
-        4 => HandType::Pair,
-        5 => HandType::HighCard,
-        n => panic!("Invalid hand {n}"),
+// Hands only compare properly if they were parsed under the same rules.  Two hands are
+// equal if they're equally strong, whatever their cards and bids.
+impl Ord for Hand {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.hand_type
+            .cmp(&other.hand_type)
+            .then_with(|| self.tie_break.cmp(&other.tie_break))
     }
 }
 
-impl Hand {
-    fn sort(&self, other: &Hand, part2: bool) -> Ordering {
-        let rank_ordering = if part2 {
-            self.hand_type_pt2.cmp(&other.hand_type_pt2)
-        } else {
-            self.hand_type_pt1.cmp(&other.hand_type_pt1)
-        };
-        match rank_ordering {
-            Ordering::Less => Ordering::Less,
-            Ordering::Greater => Ordering::Greater,
-            Ordering::Equal => {
-                // Same rank.  Compare card values
-                let cards = if part2 {
-                    self.cards_pt2.iter().zip(&other.cards_pt2)
-                } else {
-                    self.cards_pt1.iter().zip(&other.cards_pt1)
-                };
-                for (a, b) in cards {
-                    match a.cmp(b) {
-                        Ordering::Less => return Ordering::Less,
-                        Ordering::Greater => return Ordering::Greater,
-                        // Match - move to next card
-                        Ordering::Equal => (),
-                    }
-                }
-                Ordering::Equal
-            }
-        }
+impl PartialEq for Hand {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Hand {}
+
+impl PartialOrd for Hand {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
     }
 }
 
-fn solve(hands: &[Hand]) -> usize {
+// Parse the hands and sort them, weakest first.
+fn rank_hands(input: Span, rules: &Rules) -> ParseResult<Vec<Hand>> {
+    let mut hands = input
+        .lines()
+        .map(|line| Hand::parse(line, rules))
+        .collect::<ParseResult<Vec<_>>>()?;
+    hands.sort();
+    Ok(hands)
+}
+
+// Add up the bids times the ranks.
+fn total_winnings(hands: &[Hand]) -> u64 {
     hands
         .iter()
-        .enumerate()
-        .map(|(i, hand)| hand.bid * (i + 1))
-        .sum::<usize>()
+        .zip(1..)
+        .map(|(hand, rank)| hand.bid * rank)
+        .sum()
 }
 
-pub fn run(input_path: String) {
+// List the (sorted) hands with their types, ranks and winnings, then a count of each type.
+fn report(hands: &[Hand]) {
+    for (hand, rank) in hands.iter().zip(1u64..) {
+        let cards = hand.cards.iter().collect::<String>();
+        let wild = match hand.wild_as {
+            Some(card) => format!(" (wild as {card})"),
+            None => String::new(),
+        };
+        println!(
+            "{cards} {:>5}: {} -> {}{wild}, rank {rank}, wins {}",
+            hand.bid,
+            hand.raw_type.name(),
+            hand.hand_type.name(),
+            hand.bid * rank
+        );
+    }
+    println!();
+    println!("{:<16} {:>6} {:>6}", "Type", "Raw", "Wild");
+    for hand_type in HAND_TYPES {
+        let raw = hands.iter().filter(|h| h.raw_type == hand_type).count();
+        let wild = hands.iter().filter(|h| h.hand_type == hand_type).count();
+        println!("{:<16} {raw:>6} {wild:>6}", hand_type.name());
+    }
+    println!();
+}
+
+pub fn run(input_path: String) -> Result<(), String> {
     let input = std::fs::read_to_string(input_path).unwrap();
-    let mut hands = input.lines().map(Hand::from_str).collect::<Vec<_>>();
-    let mut hands2 = hands.clone();
-    // Now sort the hands.
-    hands.sort_by(|left, right| left.sort(right, false));
-    hands2.sort_by(|left, right| left.sort(right, true));
+    let input = Span::new(&input);
+
+    let tie_break = match opts::value("tie-break").as_deref() {
+        None | Some("dealt") => TieBreak::Dealt,
+        Some("poker") => TieBreak::Poker,
+        Some(other) => return Err(format!("Unknown tie-break {other}")),
+    };
+    let part1_rules = Rules {
+        tie_break,
+        ..Rules::part1()
+    };
+    let mut part2_rules = Rules {
+        tie_break,
+        ..Rules::part2()
+    };
+    if let Some(wild) = opts::value("wild") {
+        part2_rules.wild = wild.chars().collect();
+    }
 
-    let part1 = solve(&hands);
-    let part2 = solve(&hands2);
+    let part1 = total_winnings(&rank_hands(input, &part1_rules)?);
+    let hands = rank_hands(input, &part2_rules)?;
+    if opts::flag("report") {
+        report(&hands);
+    }
+    let part2 = total_winnings(&hands);
 
     println!("Part 1: {}", part1);
     println!("Part 2: {}", part2);
+    Ok(())
 }
@@ -6,8 +6,22 @@
 // more details in the inline comments.
 // Thanks to my colleagues who shared insights that helped me find this solution and get
 // over the line!
+//
+// Those assumptions are now checked rather than asserted: each ghost's walk is followed until
+// it starts going round in circles, and the LCM is only used if every ghost is on a target at
+// exactly the multiples of some period - otherwise it's an error saying which ghost doesn't
+// fit.  `--analyse` prints all that for each ghost (the steps before the circle, how long the
+// circle is, and when it's on a target), along with the strongly connected components and
+// the targets each start can reach.  `--dot` prints the network as a Graphviz graph.
 
-use std::collections::HashMap;
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use crate::{
+    intern::{Interner, NameId},
+    opts,
+    parse::{ParseResult, Span},
+};
 
 #[derive(Clone, Debug, Copy)]
 enum Dir {
@@ -16,113 +30,451 @@ enum Dir {
 }
 
 impl Dir {
-    fn from_char(c: char) -> Self {
-        match c {
-            'L' => Dir::Left,
-            'R' => Dir::Right,
-            _ => panic!("Invalid char"),
+    fn parse(s: Span) -> ParseResult<Self> {
+        match s.as_str() {
+            "L" => Ok(Dir::Left),
+            "R" => Ok(Dir::Right),
+            c => Err(s.error(format!("Invalid direction {c}"))),
         }
     }
 }
 
-fn solve<I: IntoIterator<Item = Dir> + Clone>(
-    dirs: I,
-    nodes: &HashMap<String, (String, String)>,
-    part2: bool,
-) -> u64
-where
-    <I as IntoIterator>::IntoIter: Clone,
-{
-    // Note down the length of the direction cycle.
-    let dir_count = dirs.clone().into_iter().count() as u64;
-    // Part 1 wants route from AAA -> ZZZ
-    // Part 2 wants routes from all ??A to any ??Z
-    let (start_nodes, targets) = if part2 {
-        (
-            nodes
-                .keys()
-                .filter(|k| k.ends_with('A'))
-                .map(|k| k.as_str())
-                .collect::<Vec<_>>(),
-            nodes
-                .keys()
-                .filter(|k| k.ends_with('Z'))
-                .map(|k| k.as_str())
-                .collect::<Vec<_>>(),
-        )
-    } else {
-        (vec!["AAA"], vec!["ZZZ"])
-    };
+// The map, with each node's (left, right) neighbours indexed by the node's ID.
+struct Network {
+    names: Interner,
+    links: Vec<(NameId, NameId)>,
+}
 
-    // For each source, count the steps to reach a target.
-    // We want the minimum steps for all the targets to be reached at once.
-    // It turns out with our inputs this is the lowest common multiple of
-    // the individual steps, and the input direction cycle length.
-    // This only works as the next step after each target matches the step from the source.
-    // The code asserts this is the case by looping through again to reach the next target.
-    //
-    // If this weren't true, we'd need to keep track of how long it takes to get back
-    // to another target, and the position modulo length of direction lists, and do some
-    // modular arithmetic fun...
-    let steps = start_nodes.iter().map(|&start| {
-        let mut steps = 0u64;
-        let mut first_target = None;
-        let mut current = start;
-        for dir in dirs.clone().into_iter().cycle() {
-            steps += 1;
+impl Network {
+    fn step(&self, node: NameId, dir: Dir) -> NameId {
+        match dir {
+            Dir::Left => self.links[node].0,
+            Dir::Right => self.links[node].1,
+        }
+    }
+
+    fn ends_with(&self, c: char) -> Vec<NameId> {
+        self.names
+            .iter()
+            .filter(|(_, name)| name.ends_with(c))
+            .map(|(id, _)| id)
+            .collect()
+    }
+
+    // Graphviz, with the starts in green and the targets in red.  Names are quoted, since
+    // a bare ID in DOT can't start with a digit (like `11A`).
+    fn to_dot(&self) -> String {
+        let mut out = String::from("digraph network {\n");
+        for (id, name) in self.names.iter() {
+            if name.ends_with('A') {
+                writeln!(out, "  \"{name}\" [style=filled, fillcolor=palegreen];").unwrap();
+            } else if name.ends_with('Z') {
+                writeln!(out, "  \"{name}\" [style=filled, fillcolor=salmon];").unwrap();
+            }
+            let (left, right) = self.links[id];
+            let (left, right) = (self.names.name(left), self.names.name(right));
+            if left == right {
+                writeln!(out, "  \"{name}\" -> \"{left}\" [label=\"LR\"];").unwrap();
+            } else {
+                writeln!(out, "  \"{name}\" -> \"{left}\" [label=\"L\"];").unwrap();
+                writeln!(out, "  \"{name}\" -> \"{right}\" [label=\"R\"];").unwrap();
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    // Tarjan's algorithm, giving the component index of each node.  (Done with an explicit
+    // stack, as the network is deep enough to worry about recursion.)
+    fn components(&self) -> Vec<usize> {
+        const UNSEEN: usize = usize::MAX;
+        let count = self.links.len();
+        let mut index = vec![UNSEEN; count];
+        let mut low_link = vec![0; count];
+        let mut on_stack = vec![false; count];
+        let mut stack = vec![];
+        let mut component = vec![UNSEEN; count];
+        let mut next_index = 0;
+        let mut next_component = 0;
+
+        for root in 0..count {
+            if index[root] != UNSEEN {
+                continue;
+            }
+            // (node, how many of its links we've followed so far)
+            let mut work = vec![(root, 0)];
+            while let Some(&mut (node, ref mut followed)) = work.last_mut() {
+                if *followed == 0 {
+                    index[node] = next_index;
+                    low_link[node] = next_index;
+                    next_index += 1;
+                    stack.push(node);
+                    on_stack[node] = true;
+                }
+                let (left, right) = self.links[node];
+                if let Some(&next) = [left, right].get(*followed) {
+                    *followed += 1;
+                    if index[next] == UNSEEN {
+                        work.push((next, 0));
+                    } else if on_stack[next] {
+                        low_link[node] = low_link[node].min(index[next]);
+                    }
+                    continue;
+                }
 
-            let entry = nodes.get(current).unwrap();
-            let next = match dir {
-                Dir::Left => entry.0.as_str(),
-                Dir::Right => entry.1.as_str(),
-            };
-
-            if let Some(t) = targets.iter().find(|&&target| target == next) {
-                if let Some((loc, count)) = first_target {
-                    // Reached a target again - check it's the same one so the cycle
-                    // assumptions hold.
-                    assert_eq!(loc, t);
-                    assert_eq!(steps, count);
-                    break;
-                } else {
-                    // Reached the target.  Save off the name and count, and keep going.
-                    first_target = Some((t, steps));
-                    steps = 0;
+                // Done with this node.
+                work.pop();
+                if let Some(&(parent, _)) = work.last() {
+                    low_link[parent] = low_link[parent].min(low_link[node]);
+                }
+                if low_link[node] == index[node] {
+                    loop {
+                        let member = stack.pop().unwrap();
+                        on_stack[member] = false;
+                        component[member] = next_component;
+                        if member == node {
+                            break;
+                        }
+                    }
+                    next_component += 1;
                 }
             }
+        }
+        component
+    }
 
-            current = next;
+    // Everything reachable from the start, going either way at every node.
+    fn reachable(&self, start: NameId) -> Vec<bool> {
+        let mut seen = vec![false; self.links.len()];
+        seen[start] = true;
+        let mut stack = vec![start];
+        while let Some(node) = stack.pop() {
+            let (left, right) = self.links[node];
+            for next in [left, right] {
+                if !seen[next] {
+                    seen[next] = true;
+                    stack.push(next);
+                }
+            }
         }
-        steps
-    });
+        seen
+    }
+}
 
-    // Using the `num` crate as I can't be bothered to implement Euclid's algorithm myself.
-    steps.fold(dir_count, |total, to_target| {
-        num::integer::lcm(total, to_target)
-    })
+// Where a ghost's walk ends up.  Its position after n steps depends only on where it was and
+// how far through the directions it is, so it must eventually loop.
+#[derive(Debug, Clone)]
+struct Ghost {
+    // How many steps before it starts going round the loop.
+    tail: u64,
+    // How long the loop is.
+    cycle: u64,
+    // The steps (before the loop comes round again) on which it's on a target.  Any at or
+    // after `tail` repeat every `cycle` steps.
+    target_offsets: Vec<u64>,
+    // The nodes visited on the loop.
+    loop_nodes: BTreeSet<NameId>,
 }
 
-pub fn run(input_path: String) {
-    let input = std::fs::read_to_string(input_path).unwrap();
-    let (dir_input, map_input) = input.split_once("\n\n").unwrap();
-    let dirs = dir_input.chars().map(Dir::from_char);
+impl Ghost {
+    fn walk(start: NameId, dirs: &[Dir], network: &Network, targets: &[NameId]) -> Self {
+        let states = network.links.len() * dirs.len();
+        let mut seen_at = vec![None; states];
+        let mut path = vec![];
+        let mut node = start;
+        let mut steps = 0u64;
+        let tail = loop {
+            let dir_idx = (steps % dirs.len() as u64) as usize;
+            let state = node * dirs.len() + dir_idx;
+            if let Some(first) = seen_at[state] {
+                break first;
+            }
+            seen_at[state] = Some(steps);
+            path.push(node);
+            node = network.step(node, dirs[dir_idx]);
+            steps += 1;
+        };
+        Ghost {
+            tail,
+            cycle: steps - tail,
+            target_offsets: (0..steps)
+                .filter(|&n| targets.contains(&path[n as usize]))
+                .collect(),
+            loop_nodes: path[tail as usize..].iter().copied().collect(),
+        }
+    }
+
+    // The LCM answer is only right if the ghost is on a target at exactly the multiples of
+    // some period.  (It might be a factor of the loop length, if the nodes repeat before the
+    // directions do.)
+    fn fits_lcm(&self) -> bool {
+        match self.target_offsets.first() {
+            Some(&period) if period >= self.tail && self.cycle.is_multiple_of(period) => self
+                .target_offsets
+                .iter()
+                .copied()
+                .eq((period..=self.cycle).step_by(period as usize)),
+            _ => false,
+        }
+    }
+}
+
+fn analyse(dirs: &[Dir], network: &Network) {
+    let starts = network.ends_with('A');
+    let targets = network.ends_with('Z');
+    let component = network.components();
+    let component_size = |c: usize| component.iter().filter(|&&other| other == c).count();
+    let names = |nodes: &mut dyn Iterator<Item = NameId>| {
+        nodes
+            .map(|id| network.names.name(id).to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
 
-    // Regex cos life's too short.
-    let map_pattern = regex::Regex::new(r"([A-Z1-9]+) = \(([A-Z1-9]+), ([A-Z1-9]+)\)").unwrap();
-    let nodes = map_input
+    let mut all_fit = true;
+    for &start in &starts {
+        let ghost = Ghost::walk(start, dirs, network, &targets);
+        all_fit &= ghost.fits_lcm();
+        let reachable = network.reachable(start);
+        let loop_components = ghost
+            .loop_nodes
+            .iter()
+            .map(|&node| component[node])
+            .collect::<BTreeSet<_>>();
+
+        println!("{}:", network.names.name(start));
+        println!(
+            "  starts in component {} ({} nodes), loops in {}",
+            component[start],
+            component_size(component[start]),
+            loop_components
+                .iter()
+                .map(|&c| format!("component {c} ({} nodes)", component_size(c)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        println!(
+            "  can reach targets: {}",
+            names(&mut targets.iter().copied().filter(|&t| reachable[t]))
+        );
+        println!(
+            "  tail {}, cycle {}, on a target after {}{}",
+            ghost.tail,
+            ghost.cycle,
+            ghost
+                .target_offsets
+                .iter()
+                .map(|n| n.to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+            if ghost.fits_lcm() {
+                ""
+            } else {
+                " - doesn't fit the LCM assumption"
+            }
+        );
+    }
+    println!(
+        "The LCM assumption {}",
+        if starts.len() == 1 {
+            "isn't needed for a single ghost"
+        } else if all_fit {
+            "holds"
+        } else {
+            "doesn't hold"
+        }
+    );
+    println!();
+}
+
+// The fewest steps until every ghost is on a target at once.
+// Part 1 wants route from AAA -> ZZZ
+// Part 2 wants routes from all ??A to any ??Z
+fn solve(
+    dirs: &[Dir],
+    network: &Network,
+    start_nodes: &[NameId],
+    targets: &[NameId],
+) -> Result<u64, String> {
+    let ghosts = start_nodes
+        .iter()
+        .map(|&start| Ghost::walk(start, dirs, network, targets))
+        .collect::<Vec<_>>();
+
+    // With one ghost, it's just the first time it gets to a target.
+    if let [ghost] = ghosts.as_slice() {
+        return ghost
+            .target_offsets
+            .iter()
+            .copied()
+            .find(|&n| n > 0)
+            .ok_or_else(|| {
+                format!(
+                    "{} never reaches a target",
+                    network.names.name(start_nodes[0])
+                )
+            });
+    }
+
+    // Otherwise it turns out with our inputs that each ghost is on a target at exactly the
+    // multiples of the steps to its first one, so the answer is the lowest common multiple
+    // of those.  Check that's true rather than just hoping: if it weren't, we'd need to
+    // line up the tails and cycles with the Chinese remainder theorem, and some of the
+    // targets before each loop, which is modular arithmetic fun for another day...
+    if ghosts.is_empty() {
+        return Err("No starting nodes".to_string());
+    }
+    let mut total = 1u64;
+    for (ghost, &start) in ghosts.iter().zip(start_nodes) {
+        if !ghost.fits_lcm() {
+            return Err(format!(
+                "The ghost from {} doesn't fit the LCM assumption (tail {}, cycle {}, on a \
+                 target after {:?})",
+                network.names.name(start),
+                ghost.tail,
+                ghost.cycle,
+                ghost.target_offsets
+            ));
+        }
+        // Using the `num` crate as I can't be bothered to implement Euclid's algorithm myself.
+        total = num::integer::lcm(total, ghost.target_offsets[0]);
+    }
+    Ok(total)
+}
+
+fn parse(input: Span) -> ParseResult<(Vec<Dir>, Network)> {
+    let (dir_input, map_input) = input.split_once("\n\n")?;
+    let dirs = dir_input
+        .trim()
+        .chars()
+        .map(Dir::parse)
+        .collect::<ParseResult<Vec<_>>>()?;
+
+    // Regex cos life's too short.  (Well, a simple capture pattern these days.)
+    let entries = map_input
         .lines()
         .map(|l| {
-            let matches = map_pattern.captures(l).unwrap();
-            let src = matches.get(1).unwrap().as_str();
-            let left = matches.get(2).unwrap().as_str();
-            let right = matches.get(3).unwrap().as_str();
-            (src.to_string(), (left.to_string(), right.to_string()))
+            let [src, left, right] = l.captures("{} = ({}, {})")?;
+            Ok((src, left, right))
         })
-        .collect::<HashMap<_, _>>();
+        .collect::<ParseResult<Vec<_>>>()?;
 
-    let part1 = solve(dirs.clone(), &nodes, false);
+    // Intern the sources first, so each node's ID is its index in the links list.
+    let mut names = Interner::new();
+    for (src, _, _) in &entries {
+        names.intern(src.as_str());
+    }
+    let lookup = |node: &Span| {
+        names
+            .get(node.as_str())
+            .ok_or_else(|| node.error(format!("Unknown node {}", node.as_str())))
+    };
+    let links = entries
+        .iter()
+        .map(|(_, left, right)| Ok((lookup(left)?, lookup(right)?)))
+        .collect::<ParseResult<_>>()?;
+    Ok((dirs, Network { names, links }))
+}
+
+pub fn run(input_path: String) -> Result<(), String> {
+    let input = std::fs::read_to_string(input_path).unwrap();
+    let (dirs, network) = parse(Span::new(&input))?;
+    if opts::flag("dot") {
+        print!("{}", network.to_dot());
+    }
+    if opts::flag("analyse") {
+        analyse(&dirs, &network);
+    }
+
+    let [start, target] = ["AAA", "ZZZ"].map(|name| {
+        network
+            .names
+            .get(name)
+            .ok_or_else(|| Span::new(&input).error(format!("No node {name}")))
+    });
+    let part1 = solve(&dirs, &network, &[start?], &[target?])?;
     println!("Part 1: {}", part1);
 
-    let part2 = solve(dirs, &nodes, true);
+    let part2 = solve(
+        &dirs,
+        &network,
+        &network.ends_with('A'),
+        &network.ends_with('Z'),
+    )?;
     println!("Part 2: {}", part2);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solve_example(input: &str, start: char, target: char) -> Result<u64, String> {
+        let (dirs, network) = parse(Span::new(input)).unwrap();
+        solve(
+            &dirs,
+            &network,
+            &network.ends_with(start),
+            &network.ends_with(target),
+        )
+    }
+
+    #[test]
+    fn part1_examples() {
+        let first = "\
+RL
+
+AAA = (BBB, CCC)
+BBB = (DDD, EEE)
+CCC = (ZZZ, GGG)
+DDD = (DDD, DDD)
+EEE = (EEE, EEE)
+GGG = (GGG, GGG)
+ZZZ = (ZZZ, ZZZ)
+";
+        assert_eq!(solve_example(first, 'A', 'Z'), Ok(2));
+        // Once at ZZZ it stays there, so it's on a target every step after that.
+        let second = "\
+LLR
+
+AAA = (BBB, BBB)
+BBB = (AAA, ZZZ)
+ZZZ = (ZZZ, ZZZ)
+";
+        assert_eq!(solve_example(second, 'A', 'Z'), Ok(6));
+    }
+
+    #[test]
+    fn part2_example() {
+        let input = "\
+LR
+
+11A = (11B, XXX)
+11B = (XXX, 11Z)
+11Z = (11B, XXX)
+22A = (22B, XXX)
+22B = (22C, 22C)
+22C = (22Z, 22Z)
+22Z = (22B, 22B)
+XXX = (XXX, XXX)
+";
+        assert_eq!(solve_example(input, 'A', 'Z'), Ok(6));
+    }
+
+    #[test]
+    fn lcm_assumption_broken() {
+        // 22A only gets to a target once, on its way into a loop without one.
+        let input = "\
+L
+
+11A = (11Z, 11Z)
+11Z = (11Z, 11Z)
+22A = (22Z, 22Z)
+22Z = (22B, 22B)
+22B = (22C, 22C)
+22C = (22B, 22B)
+";
+        let err = solve_example(input, 'A', 'Z').unwrap_err();
+        assert!(err.contains("from 22A"), "{err}");
+    }
 }
@@ -2,61 +2,210 @@
 // There might be some clever maths here, but just following the basic approach
 // completes in < 1 millisecond.
 // Part 2 was a pleasingly simple extension as well.
+//
+// The clever maths is Newton's forward differences: if the first number of each row of
+// differences is D0, D1, D2..., then the nth number of the sequence is the sum of
+// (n choose k) * Dk.  That works for any n, including negative ones, so rather than
+// extending every row by one in each direction we can jump straight to (say) the millionth
+// number.  Everything's done with big integers so that doesn't overflow, and a sequence
+// whose differences never get to zero is an error rather than a guess.
+// `--at=1000000` adds up the numbers at that index (0 being the first number in the input),
+// and `--degrees` shows the degree of the polynomial behind each sequence.
+// `--formula` goes further and prints the polynomial itself, which generally has fractions
+// in (eg the triangle numbers are 1/2 n^2 + 1/2 n), after checking it gives back the input.
 
-// Extrapolate the sequence out in both directions.
-fn extrapolate_sequence(seq: Vec<i32>) -> (i32, i32) {
-    // Step 1 - calculate the difference between each consecutive item.
-    // Then repeat on *that* sequence, until we get all zeroes.
-    let mut hists = vec![seq];
-    loop {
-        // Get the differences for the most recent history
-        let latest = hists.last().unwrap();
-        let diffs = latest
-            .windows(2)
-            .map(|set| set[1] - set[0])
-            .collect::<Vec<_>>();
-
-        if diffs.iter().all(|&d| d == 0) {
-            break;
+use std::fmt;
+
+use num::{BigInt, BigRational, One, Signed, Zero};
+
+use crate::opts;
+use crate::parse::{ParseResult, Span};
+
+// A sequence, stored as the first number of each row of differences.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Newton {
+    diffs: Vec<BigInt>,
+    // How many numbers there were to start with.
+    len: usize,
+}
+
+impl Newton {
+    // Calculate the difference between each consecutive item, then repeat on *that*
+    // sequence until we get all zeroes.  Those rows don't add anything, so stop there.
+    fn from_sequence(seq: &[BigInt]) -> Result<Self, String> {
+        let mut row = seq.to_vec();
+        let mut diffs = vec![];
+        while !row.iter().all(Zero::is_zero) {
+            if row.len() == 1 {
+                return Err(format!(
+                    "differences never reach zero ({} numbers isn't enough)",
+                    seq.len()
+                ));
+            }
+            diffs.push(row[0].clone());
+            row = row.windows(2).map(|set| &set[1] - &set[0]).collect();
         }
-        hists.push(diffs);
+        Ok(Newton {
+            diffs,
+            len: seq.len(),
+        })
     }
 
-    // Now iterate backwards over the history, and store the extra first and last element in each sequence
-    // by respectively subtracting and adding the previous difference to the first and last
-    // element.
-    //
-    // We end up with the extra first and last element of the original sequence, whcih is what we want
-    // to return.
-    hists
-        .iter()
-        .rev()
-        .fold((0, 0), |(first_diff, last_diff), this| {
-            (
-                this.first().unwrap() - first_diff,
-                this.last().unwrap() + last_diff,
-            )
-        })
+    // The degree of the polynomial, or None if the sequence is all zeroes.
+    fn degree(&self) -> Option<usize> {
+        self.diffs.len().checked_sub(1)
+    }
+
+    // The number at any index.
+    fn at(&self, n: &BigInt) -> BigInt {
+        // (n choose k) for each k in turn.  n(n-1)...(n-k+1) is always divisible by k!, even
+        // when n is negative, so the division is exact.
+        let mut choose = BigInt::one();
+        let mut total = BigInt::zero();
+        for (k, diff) in self.diffs.iter().enumerate() {
+            total += &choose * diff;
+            choose = choose * (n - k) / (k + 1);
+        }
+        total
+    }
 }
 
-pub fn run(input_path: String) {
+// A polynomial in n, with its coefficients lowest power first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Polynomial {
+    coeffs: Vec<BigRational>,
+}
+
+impl Polynomial {
+    // Expand out the sum of Dk * n(n-1)...(n-k+1) / k!.
+    fn from_newton(newton: &Newton) -> Self {
+        let mut coeffs = vec![BigRational::zero(); newton.diffs.len()];
+        // n(n-1)...(n-k+1) / k!, built up one k at a time.
+        let mut falling = vec![BigRational::one()];
+        for (k, diff) in newton.diffs.iter().enumerate() {
+            for (coeff, f) in coeffs.iter_mut().zip(&falling) {
+                *coeff += f * BigRational::from_integer(diff.clone());
+            }
+            // Multiply by (n - k) / (k + 1).
+            let scale = BigRational::from_integer(BigInt::from(k + 1));
+            let mut next = vec![BigRational::zero(); falling.len() + 1];
+            for (power, f) in falling.iter().enumerate() {
+                let f = f / &scale;
+                next[power] -= &f * BigRational::from_integer(BigInt::from(k));
+                next[power + 1] += f;
+            }
+            falling = next;
+        }
+        Polynomial { coeffs }
+    }
+
+    fn at(&self, n: &BigRational) -> BigRational {
+        self.coeffs
+            .iter()
+            .rev()
+            .fold(BigRational::zero(), |total, coeff| total * n + coeff)
+    }
+}
+
+// Highest power first, like `1/2 n^2 - 3 n + 1`.
+impl fmt::Display for Polynomial {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut first = true;
+        for (power, coeff) in self.coeffs.iter().enumerate().rev() {
+            if coeff.is_zero() {
+                continue;
+            }
+            match (first, coeff.is_negative()) {
+                (true, true) => write!(f, "-")?,
+                (true, false) => {}
+                (false, true) => write!(f, " - ")?,
+                (false, false) => write!(f, " + ")?,
+            }
+            first = false;
+            let size = coeff.abs();
+            if power == 0 || !size.is_one() {
+                write!(f, "{size}")?;
+                if power > 0 {
+                    write!(f, " ")?;
+                }
+            }
+            match power {
+                0 => {}
+                1 => write!(f, "n")?,
+                _ => write!(f, "n^{power}")?,
+            }
+        }
+        if first {
+            write!(f, "0")?;
+        }
+        Ok(())
+    }
+}
+
+pub fn run(input_path: String) -> Result<(), String> {
     let input = std::fs::read_to_string(input_path).unwrap();
+    let at = opts::value("at")
+        .map(|n| {
+            n.parse::<BigInt>()
+                .map_err(|e| format!("Bad index {n}: {e}"))
+        })
+        .transpose()?;
 
-    // Parse each line into a sequence and calculate its extrapolated
-    // first and last.
+    // Parse each line into a sequence, and work out its differences.
     // Input has negative numbers, so use signed integers!
-    let (first_sum, lasts_sum) = input
+    let lines = Span::new(&input)
         .lines()
-        .map(|line| {
-            let sequence = line
-                .split_whitespace()
-                .map(|word| word.parse::<i32>().unwrap())
-                .collect::<Vec<_>>();
-            extrapolate_sequence(sequence)
-        })
-        // We want the separate sums of all the firsts and all the lasts.
-        .fold((0, 0), |total, next| (total.0 + next.0, total.1 + next.1));
+        .map(|line| line.numbers::<BigInt>())
+        .collect::<ParseResult<Vec<_>>>()?;
+    let sequences = lines
+        .iter()
+        .zip(Span::new(&input).lines())
+        .map(|(seq, line)| Newton::from_sequence(seq).map_err(|e| line.error(e)))
+        .collect::<ParseResult<Vec<_>>>()?;
 
+    if opts::flag("degrees") {
+        for (idx, seq) in sequences.iter().enumerate() {
+            match seq.degree() {
+                Some(degree) => println!("Sequence {}: degree {degree}", idx + 1),
+                None => println!("Sequence {}: all zeroes", idx + 1),
+            }
+        }
+    }
+
+    if opts::flag("formula") {
+        for (idx, (seq, numbers)) in sequences.iter().zip(&lines).enumerate() {
+            let poly = Polynomial::from_newton(seq);
+            // It had better give back the numbers we started with.
+            for (n, number) in numbers.iter().enumerate() {
+                let value = poly.at(&BigRational::from_integer(BigInt::from(n)));
+                if value != BigRational::from_integer(number.clone()) {
+                    return Err(format!(
+                        "Sequence {}: {poly} gives {value} at n = {n}, not {number}",
+                        idx + 1
+                    ));
+                }
+            }
+            println!(
+                "Sequence {}: {poly} (checked for n = 0..{})",
+                idx + 1,
+                numbers.len()
+            );
+        }
+    }
+
+    // Part 1 wants the next number after each sequence, part 2 the one before.
+    let mut lasts_sum = BigInt::zero();
+    let mut first_sum = BigInt::zero();
+    for seq in &sequences {
+        lasts_sum += seq.at(&BigInt::from(seq.len));
+        first_sum += seq.at(&-BigInt::one());
+    }
+
+    if let Some(at) = at {
+        let total = sequences.iter().map(|seq| seq.at(&at)).sum::<BigInt>();
+        println!("At {at}: {total}");
+    }
     println!("Part 1: {}", lasts_sum);
     println!("Part 2: {}", first_sum);
+    Ok(())
 }
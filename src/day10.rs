@@ -10,6 +10,12 @@
 // - I forgot to handle the start cell at all at first. Then when I fixed that, I got it *wrong* - but only if the S was
 // on the right edge of a horizontal segment, which none of the test examples are.
 // In the end the liberal assertions saved me, so lesson is to put those in sooner than later!
+//
+// Since then part 2 has switched to the shoelace/Pick's theorem approach from day 18, which is
+// much shorter.  The row scan is still there with `--scan`, and the tests check the two agree.
+
+use crate::opts;
+use crate::polygon;
 
 // Simple wrapper around the grid, holding the start cell separately.
 struct Grid {
@@ -250,11 +256,8 @@ fn get_inside_point_count<'a>(
     inside_count
 }
 
-pub fn run(input_path: String) {
-    let input = std::fs::read_to_string(input_path).unwrap();
-    let grid = Grid::from_str(&input);
-
-    // Keep track of the path around the loop.
+// Follow the loop round from the start, returning every cell on it in order.
+fn find_loop(grid: &Grid) -> Vec<(usize, usize)> {
     let mut this = grid.start;
     let mut path = vec![];
     loop {
@@ -267,15 +270,118 @@ pub fn run(input_path: String) {
             None => break,
         }
     }
+    path
+}
+
+// The loop is a lattice polygon with every cell of the path as a vertex, so Pick's theorem
+// (as in day 18) gives the interior count directly.
+fn count_enclosed(path: &[(usize, usize)]) -> usize {
+    let vertices = path
+        .iter()
+        .map(|&(x, y)| (x as i64, y as i64))
+        .collect::<Vec<_>>();
+    polygon::measure(&vertices).interior as usize
+}
+
+// The original approach: consider the grid row by row.
+fn scan_enclosed(grid: &Grid, path: &[(usize, usize)]) -> usize {
+    (0..grid.grid.len())
+        .map(|y| get_inside_point_count(grid, y, path.iter()))
+        .sum()
+}
+
+pub fn run(input_path: String) {
+    let input = std::fs::read_to_string(input_path).unwrap();
+    let grid = Grid::from_str(&input);
+
+    // Keep track of the path around the loop.
+    let path = find_loop(&grid);
 
     // The furthest point is half the loop length.  Add 1 to allow for odd numbers.
-    println!("Part 1: {}", (path.len() + 1) / 2);
+    println!("Part 1: {}", path.len().div_ceil(2));
 
     // For part2 we need to find howe many points are *inside* the loop.
-    // We can consider the grid row by row.
-    let part2: usize = (0..grid.grid.len())
-        .map(|y| get_inside_point_count(&grid, y, path.iter()))
-        .sum();
-
+    let part2 = if opts::flag("scan") {
+        scan_enclosed(&grid, &path)
+    } else {
+        count_enclosed(&path)
+    };
     println!("Part 2: {}", part2);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The part 2 examples, with how many tiles each encloses.
+    const EXAMPLES: [(&str, usize); 4] = [
+        (
+            "\
+...........
+.S-------7.
+.|F-----7|.
+.||.....||.
+.||.....||.
+.|L-7.F-J|.
+.|..|.|..|.
+.L--J.L--J.
+...........
+",
+            4,
+        ),
+        (
+            "\
+..........
+.S------7.
+.|F----7|.
+.||....||.
+.||....||.
+.|L-7F-J|.
+.|..||..|.
+.L--JL--J.
+..........
+",
+            4,
+        ),
+        (
+            "\
+.F----7F7F7F7F-7....
+.|F--7||||||||FJ....
+.||.FJ||||||||L7....
+FJL7L7LJLJ||LJ.L-7..
+L--J.L7...LJS7F-7L7.
+....F-J..F7FJ|L7L7L7
+....L7.F7||L7|.L7L7|
+.....|FJLJ|FJ|F7|.LJ
+....FJL-7.||.||||...
+....L---J.LJ.LJLJ...
+",
+            8,
+        ),
+        (
+            "\
+FF7FSF7F7F7F7F7F---7
+L|LJ||||||||||||F--J
+FL-7LJLJ||||||LJL-77
+F--JF--7||LJLJ7F7FJ-
+L---JF-JLJ.||-FJLJJ7
+|F|F-JF---7F7-L7L|7|
+|FFJF7L7F-JF7|JL---7
+7-L-JL7||F7|L7F-7F7|
+L.L7LFJ|||||FJL7||LJ
+L7JLJL-JLJLJL--JLJ.L
+",
+            10,
+        ),
+    ];
+
+    #[test]
+    fn pick_matches_row_scan() {
+        for (input, enclosed) in EXAMPLES {
+            let grid = Grid::from_str(input);
+            let path = find_loop(&grid);
+            assert_eq!(count_enclosed(&path), enclosed);
+            assert_eq!(scan_enclosed(&grid, &path), enclosed);
+        }
+    }
+}
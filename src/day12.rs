@@ -4,76 +4,85 @@
 //
 // Was worried about part2 until I saw some rumblings about memoization on reddit, and indeed
 // throwing in a cache brings the runtime down to a fraction of a second, even with
-// reallocating all the time.
+// reallocating all the time.  (It now uses the shared memo helper keyed on indices, so
+// doesn't even need to reallocate.)
 
-use std::collections::HashMap;
+use crate::memo::Memo;
 
 // Cache results for performance.
-// Hit and miss counts are just for interest/debugging.
-#[derive(Debug, Clone, Default)]
-struct Cache {
-    cache: HashMap<(Vec<char>, Vec<u8>), usize>,
-    hits: u64,
-    misses: u64,
-}
+// Keyed on where we are in the row and the list of runs, so we don't need to copy the
+// remaining input for every lookup.  That means the cache is only valid for one row at a time.
+type Cache = Memo<(usize, usize), usize>;
 
 // Work out how many possible runs we could have given this input.
 // We look at the next spring, and consider all possible positions that could start given the
 // other constraints; then recurse from the point after that with the remaining springs.
 // The cache is essential for part 2 to complete in reasonable time, as we can end up
-// with billions of possibilities - but many of the later possibilities repeat themselves
-// across rows.
+// with billions of possibilities - but many of the later possibilities repeat themselves.
 fn calculate_possibles(row: &[char], lengths: &[u8], cache: &mut Cache) -> usize {
-    if let Some(res) = cache.cache.get(&(row.to_vec(), lengths.to_vec())) {
-        cache.hits += 1;
-        return *res;
-    }
-    // Check we have space for all the remaining runs.  Need all their sizes,
-    // + 1 for each run after this for the space.
-    let space_needed = lengths.iter().sum::<u8>() as usize + lengths.len() - 1;
+    cache.clear();
+    possibles_from(row, lengths, 0, 0, cache)
+}
 
-    // Return count.
-    let mut possibles = 0;
+// Count the possibilities for the runs from `lengths_start` onward, in the row from `row_start`.
+fn possibles_from(
+    full_row: &[char],
+    all_lengths: &[u8],
+    row_start: usize,
+    lengths_start: usize,
+    cache: &mut Cache,
+) -> usize {
+    cache.call((row_start, lengths_start), |cache| {
+        let row = &full_row[row_start..];
+        let lengths = &all_lengths[lengths_start..];
+        // Check we have space for all the remaining runs.  Need all their sizes,
+        // + 1 for each run after this for the space.
+        let space_needed = lengths.iter().sum::<u8>() as usize + lengths.len() - 1;
 
-    // We can potentially start a run from anywhere here onward as long as we have enough space.
-    let this_run_len = lengths[0] as usize;
-    for this_run_start in 0..=row.len() - space_needed {
-        // If the previous character was a fixed #, we can't start the run here
-        // (or anywhere after this!). Break out now.
-        if this_run_start > 0 && row[this_run_start - 1] == '#' {
-            break;
-        }
-        // The next run could start here if all the following hold:
-        // The next run-length fields are not empty (.)
-        // The single field afterwards is not full (#), or we're at the end of the row.
-        if row[this_run_start..this_run_start + this_run_len]
-            .iter()
-            .all(|&r| r != '.')
-            && (this_run_start + this_run_len == row.len()
-                || row[this_run_start + this_run_len] != '#')
-        {
-            if lengths.len() == 1 {
-                // This is the final run, so a valid option - if there are not more `#`
-                if row[this_run_start + this_run_len..]
-                    .iter()
-                    .all(|&r| r != '#')
-                {
-                    possibles += 1;
+        // Return count.
+        let mut possibles = 0;
+
+        // We can potentially start a run from anywhere here onward as long as we have enough space.
+        let this_run_len = lengths[0] as usize;
+        for this_run_start in 0..=row.len() - space_needed {
+            // If the previous character was a fixed #, we can't start the run here
+            // (or anywhere after this!). Break out now.
+            if this_run_start > 0 && row[this_run_start - 1] == '#' {
+                break;
+            }
+            // The next run could start here if all the following hold:
+            // The next run-length fields are not empty (.)
+            // The single field afterwards is not full (#), or we're at the end of the row.
+            if row[this_run_start..this_run_start + this_run_len]
+                .iter()
+                .all(|&r| r != '.')
+                && (this_run_start + this_run_len == row.len()
+                    || row[this_run_start + this_run_len] != '#')
+            {
+                if lengths.len() == 1 {
+                    // This is the final run, so a valid option - if there are not more `#`
+                    if row[this_run_start + this_run_len..]
+                        .iter()
+                        .all(|&r| r != '#')
+                    {
+                        possibles += 1;
+                    }
+                } else {
+                    // This could be a valid option.  Check positions for the remaining runs.
+                    // Skip 1 further off the end to account for the space.
+                    let new_run_start = this_run_start + this_run_len + 1;
+                    possibles += possibles_from(
+                        full_row,
+                        all_lengths,
+                        row_start + new_run_start,
+                        lengths_start + 1,
+                        cache,
+                    )
                 }
-            } else {
-                // This could be a valid option.  Check positions for the remaining runs.
-                // Skip 1 further off the end to account for the space.
-                let new_run_start = this_run_start + this_run_len + 1;
-                possibles += calculate_possibles(&row[new_run_start..], &lengths[1..], cache)
             }
         }
-    }
-
-    cache
-        .cache
-        .insert((row.to_vec(), lengths.to_vec()), possibles);
-    cache.misses += 1;
-    possibles
+        possibles
+    })
 }
 
 pub fn run(input_path: String) {
@@ -90,7 +99,7 @@ pub fn run(input_path: String) {
         )
     });
 
-    let mut cache: Cache = Default::default();
+    let mut cache = Cache::new();
     let mut part1 = 0;
 
     for (row, lengths) in records.clone() {
@@ -98,7 +107,7 @@ pub fn run(input_path: String) {
     }
 
     println!("Part 1: {}", part1);
-    println!("  Cache: {} hits {} misses", cache.hits, cache.misses);
+    println!("  Cache: {}", cache.stats());
     let mut part2 = 0;
     for (row, lengths) in records {
         let mut full_row = row.clone();
@@ -113,5 +122,5 @@ pub fn run(input_path: String) {
     }
 
     println!("Part 2: {}", part2);
-    println!("  Cache: {} hits {} misses", cache.hits, cache.misses);
+    println!("  Cache: {}", cache.stats());
 }
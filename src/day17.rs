@@ -27,7 +27,7 @@ impl std::fmt::Display for Direction {
     }
 }
 
-const ALL_DIRS: &'static [Direction] = &[Direction::N, Direction::E, Direction::S, Direction::W];
+const ALL_DIRS: &[Direction] = &[Direction::N, Direction::E, Direction::S, Direction::W];
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 struct NodeEntry {
@@ -72,9 +72,8 @@ impl NodeEntry {
                 _ => None,
             })
             .and_then(|next| {
-                if part2 && self.straight_count < 4 && *dir != self.last_entered_dir {
-                    None
-                } else if *dir == self.last_entered_dir && self.straight_count >= max_straight_count
+                if (part2 && self.straight_count < 4 && *dir != self.last_entered_dir)
+                    || (*dir == self.last_entered_dir && self.straight_count >= max_straight_count)
                 {
                     None
                 } else {
@@ -101,7 +100,7 @@ struct QueueEntry(u64, NodeEntry);
 // Implement reverse ordering for a max-heap.
 impl PartialOrd for QueueEntry {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        other.0.partial_cmp(&self.0)
+        Some(self.cmp(other))
     }
 }
 
@@ -127,7 +126,7 @@ fn solve(grid: &Grid, part2: bool) -> u64 {
     candidates.push(QueueEntry(0, start.clone()));
     distances.insert(start.clone(), 0);
 
-    let mut best = std::u64::MAX;
+    let mut best = u64::MAX;
 
     while let Some(QueueEntry(_, current)) = candidates.pop() {
         let cur_d = *distances.get(&current).unwrap();
@@ -141,7 +140,7 @@ fn solve(grid: &Grid, part2: bool) -> u64 {
         }
 
         for n in current.get_neighbours(max_x, max_y, part2) {
-            let entry = distances.entry(n.clone()).or_insert(std::u64::MAX);
+            let entry = distances.entry(n.clone()).or_insert(u64::MAX);
             let new_dist = cur_d + grid[n.y][n.x] as u64;
             if new_dist < *entry {
                 *entry = new_dist;
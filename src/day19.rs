@@ -2,26 +2,32 @@
 // Part 2 more interesting.  Brute force clearly won't work (4000^4 is way too many options).
 // However, we can analyze the rule flows, and work out where the boundaries are that trigger a change.
 //  We can then use that to track the total number of acceptable parts.
-use std::{
-    collections::{HashMap, VecDeque},
-    str::FromStr,
+// Each state is a box in xmas-space, which each rule splits in two.
+// Workflows are referred to by interned ID, and stored in a list indexed by that.
+use std::collections::VecDeque;
+
+use crate::{
+    intern::{Interner, NameId},
+    interval::{Interval, IntervalBox},
+    parse::{ParseError, ParseResult, Span},
 };
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 enum RuleTarget {
     Accept,
     Reject,
-    Jump(String),
+    Jump(NameId),
 }
 
-impl FromStr for RuleTarget {
-    type Err = String;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
+impl RuleTarget {
+    fn parse(s: Span, names: &Interner) -> ParseResult<Self> {
+        match s.as_str() {
             "A" => Ok(RuleTarget::Accept),
             "R" => Ok(RuleTarget::Reject),
-            _ => Ok(RuleTarget::Jump(s.to_string())),
+            name => names
+                .get(name)
+                .map(RuleTarget::Jump)
+                .ok_or_else(|| s.error(format!("Unknown workflow {name}"))),
         }
     }
 }
@@ -42,12 +48,13 @@ struct Part {
 }
 
 impl Part {
-    fn with_attr_value(val: u64) -> Self {
-        Part {
-            x: val,
-            a: val,
-            m: val,
-            s: val,
+    fn get(&self, axis: usize) -> u64 {
+        match axis {
+            0 => self.x,
+            1 => self.m,
+            2 => self.a,
+            3 => self.s,
+            _ => panic!("bad attr"),
         }
     }
 
@@ -59,25 +66,20 @@ impl Part {
 #[derive(Debug, Clone, PartialEq, Eq)]
 struct Rule {
     cond: Cond,
-    cond_var: String,
+    // The xmas axis the condition checks.
+    cond_var: usize,
     limit: u64,
     if_true: RuleTarget,
 }
 
-fn is_accepted(part: &Part, rules: &HashMap<&str, Vec<Rule>>) -> bool {
-    let mut workflow = rules.get("in");
+fn is_accepted(part: &Part, rules: &[Vec<Rule>], start: NameId) -> bool {
+    let mut workflow = rules.get(start);
     while let Some(w) = workflow {
         for rule in w {
             let is_true = match rule.cond {
                 Cond::None => true,
                 _ => {
-                    let attr_check = match rule.cond_var.as_str() {
-                        "x" => part.x,
-                        "m" => part.m,
-                        "a" => part.a,
-                        "s" => part.s,
-                        _ => panic!("bad attr"),
-                    };
+                    let attr_check = part.get(rule.cond_var);
                     if rule.cond == Cond::Less {
                         attr_check < rule.limit
                     } else {
@@ -91,7 +93,7 @@ fn is_accepted(part: &Part, rules: &HashMap<&str, Vec<Rule>>) -> bool {
                     RuleTarget::Accept => return true,
                     RuleTarget::Reject => return false,
                     RuleTarget::Jump(t) => {
-                        workflow = rules.get(t.as_str());
+                        workflow = rules.get(*t);
                         break;
                     }
                 }
@@ -101,108 +103,90 @@ fn is_accepted(part: &Part, rules: &HashMap<&str, Vec<Rule>>) -> bool {
     unreachable!("No accept or reject rule found")
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-struct RuleState {
-    minvals: Part,
-    maxvals: Part,
+// Which axis of the xmas box an attribute name refers to.
+fn attr_axis(name: Span) -> ParseResult<usize> {
+    match name.as_str() {
+        "x" => Ok(0),
+        "m" => Ok(1),
+        "a" => Ok(2),
+        "s" => Ok(3),
+        _ => Err(name.error("bad attr")),
+    }
 }
 
-fn count_acceptable_parts(rules: &HashMap<&str, Vec<Rule>>, max_attr: u64) -> u64 {
-    let start_rules = rules.get("in").unwrap();
+impl Rule {
+    // Either `{attr}<{limit}:{target}`, `{attr}>{limit}:{target}` or just `{target}`.
+    fn parse(rule: Span, names: &Interner) -> ParseResult<Self> {
+        if !rule.as_str().contains(':') {
+            return Ok(Rule {
+                cond: Cond::None,
+                cond_var: 0,
+                limit: 0,
+                if_true: RuleTarget::parse(rule, names)?,
+            });
+        }
+        let (cond_str, target) = rule.split_once(":")?;
+        let (cond, (cond_var, limit)) = if cond_str.as_str().contains('<') {
+            (Cond::Less, cond_str.split_once("<")?)
+        } else {
+            (Cond::Greater, cond_str.split_once(">")?)
+        };
+        Ok(Rule {
+            cond,
+            cond_var: attr_axis(cond_var)?,
+            limit: limit.parse()?,
+            if_true: RuleTarget::parse(target, names)?,
+        })
+    }
+}
+
+// The set of parts still under consideration: a 4-dimensional box of xmas values.
+type RuleState = IntervalBox<4>;
+
+fn count_acceptable_parts(rules: &[Vec<Rule>], start: NameId, max_attr: u64) -> u64 {
+    let start_rules = &rules[start];
 
     // Queue of rules to process.
     let mut stack = VecDeque::new();
     stack.push_back((
         start_rules,
         0,
-        RuleState {
-            minvals: Part::with_attr_value(1),
-            maxvals: Part::with_attr_value(max_attr + 1),
-        },
+        RuleState::cube(Interval::new(1, max_attr as i64 + 1)),
     ));
 
     let mut count = 0;
 
     while let Some((workflow, idx, state)) = stack.pop_front() {
         let rule = &workflow[idx];
-        let mut true_state = state.clone();
-        let mut false_state = state.clone();
-        match rule.cond {
-            Cond::None => (),
-            Cond::Less => {
-                match rule.cond_var.as_str() {
-                    // The "if-true" maximum is the smaller of the current maximum, and the rule limit, down to the minimum.
-                    // The "if-false" minimum is the larger of the current minimum, and the rule limit, up to the maximum.
-                    "x" => {
-                        true_state.maxvals.x = state.maxvals.x.min(rule.limit).max(state.minvals.x);
-                        false_state.minvals.x = state.minvals.x.max(rule.limit).min(state.maxvals.x)
-                    }
-                    "m" => {
-                        true_state.maxvals.m = state.maxvals.m.min(rule.limit).max(state.minvals.m);
-                        false_state.minvals.m = state.minvals.m.max(rule.limit).min(state.maxvals.m)
-                    }
-                    "a" => {
-                        true_state.maxvals.a = state.maxvals.a.min(rule.limit).max(state.minvals.a);
-                        false_state.minvals.a = state.minvals.a.max(rule.limit).min(state.maxvals.a)
-                    }
-                    "s" => {
-                        true_state.maxvals.s = state.maxvals.s.min(rule.limit).max(state.minvals.s);
-                        false_state.minvals.s = state.minvals.s.max(rule.limit).min(state.maxvals.s)
-                    }
-                    _ => panic!("bad attr"),
-                }
-            }
+        // Split the box at the rule limit, into the parts where the rule is true and false.
+        // Either may be empty, in which case there's nothing to follow on that side.
+        let (true_state, false_state) = match rule.cond {
+            Cond::None => (Some(state), None),
+            Cond::Less => state.split_at(rule.cond_var, rule.limit as i64),
             Cond::Greater => {
-                match rule.cond_var.as_str() {
-                    // The "if-true" minimum is the larger of the current minimum, and the rule limit, up to the maximum.
-                    // The "if-false" maximum is the smaller of the current maximum, and one above the rule limit, down to the minimum.
-                    "x" => {
-                        true_state.minvals.x =
-                            state.minvals.x.max(rule.limit + 1).min(state.maxvals.x);
-                        false_state.maxvals.x =
-                            state.maxvals.x.min(rule.limit + 1).max(state.minvals.x)
-                    }
-                    "m" => {
-                        true_state.minvals.m =
-                            state.minvals.m.max(rule.limit + 1).min(state.maxvals.m);
-                        false_state.maxvals.m =
-                            state.maxvals.m.min(rule.limit + 1).max(state.minvals.m);
-                    }
-                    "a" => {
-                        true_state.minvals.a =
-                            state.minvals.a.max(rule.limit + 1).min(state.maxvals.a);
-                        false_state.maxvals.a =
-                            state.maxvals.a.min(rule.limit + 1).max(state.minvals.a);
-                    }
-                    "s" => {
-                        true_state.minvals.s =
-                            state.minvals.s.max(rule.limit + 1).min(state.maxvals.s);
-                        false_state.maxvals.s =
-                            state.maxvals.s.min(rule.limit + 1).max(state.minvals.s);
-                    }
-                    _ => panic!("bad attr"),
-                };
+                let (below, above) = state.split_at(rule.cond_var, rule.limit as i64 + 1);
+                (above, below)
             }
         };
 
         // If there's another rule after this one, add it to the stack as the false option
-        if idx + 1 < workflow.len() {
-            assert_ne!(rule.cond, Cond::None);
+        if let Some(false_state) = false_state {
+            assert!(idx + 1 < workflow.len());
             stack.push_back((workflow, idx + 1, false_state));
         }
 
         // Add the count or next steps for true state
+        let Some(true_state) = true_state else {
+            continue;
+        };
         match &rule.if_true {
             RuleTarget::Accept => {
                 // Valid range.
-                count += (true_state.maxvals.x - true_state.minvals.x)
-                    * (true_state.maxvals.a - true_state.minvals.a)
-                    * (true_state.maxvals.s - true_state.minvals.s)
-                    * (true_state.maxvals.m - true_state.minvals.m);
+                count += true_state.volume();
             }
             RuleTarget::Reject => (),
             RuleTarget::Jump(t) => {
-                let new_workflow = rules.get(t.as_str()).unwrap();
+                let new_workflow = &rules[*t];
                 stack.push_back((new_workflow, 0, true_state))
             }
         }
@@ -211,79 +195,62 @@ fn count_acceptable_parts(rules: &HashMap<&str, Vec<Rule>>, max_attr: u64) -> u6
     count
 }
 
-pub fn run(input_path: String) {
+pub fn run(input_path: String) -> Result<(), ParseError> {
     let input = std::fs::read_to_string(input_path).unwrap();
-    let (workflows, parts) = input.split_once("\n\n").unwrap();
+    let (workflows, parts) = Span::new(&input).split_once("\n\n")?;
 
     // Parsing.  Could be faster with regex, but always fun to restict yourself to
-    // string methods.  Asumes input well-formed.
-    let rules: HashMap<&str, Vec<Rule>> = workflows
+    // string methods.
+    let workflows = workflows
         .lines()
-        .map(|line| {
-            let (name, rest) = line.split_once('{').unwrap();
-            let rule_parts = rest.trim_end_matches('}').split(',');
-            let rules = rule_parts
-                .map(|rule| {
-                    if let Some((cond, target)) = rule.split_once(':') {
-                        if let Some((cond_var, limit)) = cond.split_once('<') {
-                            Rule {
-                                cond_var: cond_var.to_string(),
-                                cond: Cond::Less,
-                                limit: limit.parse().unwrap(),
-                                if_true: target.parse().unwrap(),
-                            }
-                        } else if let Some((cond_var, limit)) = cond.split_once('>') {
-                            Rule {
-                                cond_var: cond_var.to_string(),
-                                cond: Cond::Greater,
-                                limit: limit.parse().unwrap(),
-                                if_true: target.parse().unwrap(),
-                            }
-                        } else {
-                            panic!("Invalid rule")
-                        }
-                    } else {
-                        Rule {
-                            cond: Cond::None,
-                            cond_var: String::new(),
-                            limit: 0,
-                            if_true: rule.parse().unwrap(),
-                        }
-                    }
-                })
-                .collect();
-
-            (name, rules)
+        .map(|line| line.captures("{}{{}}"))
+        .collect::<ParseResult<Vec<_>>>()?;
+    // Intern all the workflow names up front, so rules can refer to workflows defined
+    // later, and each workflow's ID is its line number.
+    let mut names = Interner::new();
+    for [name, _] in &workflows {
+        names.intern(name.as_str());
+    }
+    let rules = workflows
+        .iter()
+        .map(|[_, rule_parts]| {
+            rule_parts
+                .split(",")
+                .map(|rule| Rule::parse(rule, &names))
+                .collect()
         })
-        .collect();
+        .collect::<ParseResult<Vec<Vec<Rule>>>>()?;
+    let start = names
+        .get("in")
+        .ok_or_else(|| Span::new(&input).error("No workflow called in"))?;
 
-    let parts = parts.lines().map(|line| {
-        let attrs = line
-            .trim_end_matches('}')
-            .trim_start_matches('{')
-            .split(',');
-        let mut part = Part {
-            x: 0,
-            m: 0,
-            a: 0,
-            s: 0,
-        };
-        for attr in attrs {
-            let (k, v) = attr.split_once('=').unwrap();
-            match k {
-                "x" => part.x = v.parse().unwrap(),
-                "m" => part.m = v.parse().unwrap(),
-                "a" => part.a = v.parse().unwrap(),
-                "s" => part.s = v.parse().unwrap(),
-                _ => panic!("Invalid attribute"),
+    let parts = parts
+        .lines()
+        .map(|line| {
+            let [attrs] = line.captures("{{}}")?;
+            let mut part = Part {
+                x: 0,
+                m: 0,
+                a: 0,
+                s: 0,
+            };
+            for (k, v) in attrs.key_values(",", "=")? {
+                let value = v.parse()?;
+                match attr_axis(k)? {
+                    0 => part.x = value,
+                    1 => part.m = value,
+                    2 => part.a = value,
+                    _ => part.s = value,
+                }
             }
-        }
-        part
-    });
+            Ok(part)
+        })
+        .collect::<ParseResult<Vec<_>>>()?;
 
     let part1: u64 = parts
+        .iter()
         .map(|part| {
-            if is_accepted(&part, &rules) {
+            if is_accepted(part, &rules, start) {
                 part.get_value()
             } else {
                 0
@@ -292,6 +259,7 @@ pub fn run(input_path: String) {
         .sum();
     println!("Part 1: {}", part1);
 
-    let part2 = count_acceptable_parts(&rules, 4000);
+    let part2 = count_acceptable_parts(&rules, start, 4000);
     println!("Part 2: {}", part2);
+    Ok(())
 }
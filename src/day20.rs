@@ -2,8 +2,15 @@
 // Part 2 requires using the same trick as day 8, and relies on assuming we have nicely
 // lined-up cycles so we can just do an LCM on the High inputs for the final conjucntion module.
 
+// Module names are interned, so the simulation loop only deals with integer IDs.
+
 use std::collections::{HashMap, VecDeque};
 
+use crate::{
+    intern::{Interner, NameId},
+    parse::{ParseError, ParseResult, Span},
+};
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Pulse {
     Low,
@@ -12,27 +19,32 @@ enum Pulse {
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 enum Module {
-    Broadcast(Vec<String>),
-    FlipFlop(bool, Vec<String>),
-    Conjunction(HashMap<String, Pulse>, Vec<String>),
+    Broadcast(Vec<NameId>),
+    FlipFlop(bool, Vec<NameId>),
+    // Conjunctions only have a handful of inputs, so a list is quicker than a map.
+    Conjunction(Vec<(NameId, Pulse)>, Vec<NameId>),
 }
 
 impl Module {
-    fn parse(s: &str) -> (&str, Self) {
-        let (name, dests) = s.split_once(" -> ").unwrap();
-        let dest_names = dests.split(", ").map(|s| s.to_string()).collect::<Vec<_>>();
-        if name == "broadcaster" {
-            (name, Self::Broadcast(dest_names))
+    fn parse(s: Span, names: &mut Interner) -> ParseResult<(NameId, Self)> {
+        let [name, dests] = s.captures("{} -> {}")?;
+        let dest_ids = dests
+            .split(", ")
+            .map(|s| names.intern(s.as_str()))
+            .collect::<Vec<_>>();
+        let name = name.as_str();
+        Ok(if name == "broadcaster" {
+            (names.intern(name), Self::Broadcast(dest_ids))
         } else if name.starts_with('%') {
             let mname = name.trim_start_matches('%');
-            (mname, Self::FlipFlop(false, dest_names))
+            (names.intern(mname), Self::FlipFlop(false, dest_ids))
         } else {
             let mname = name.trim_start_matches('&');
-            (mname, Self::Conjunction(HashMap::new(), dest_names))
-        }
+            (names.intern(mname), Self::Conjunction(vec![], dest_ids))
+        })
     }
 
-    fn outputs(&self) -> &[String] {
+    fn outputs(&self) -> &[NameId] {
         match self {
             Self::Broadcast(d) => d,
             Self::Conjunction(_, d) => d,
@@ -40,7 +52,7 @@ impl Module {
         }
     }
 
-    fn recv(&mut self, input: Pulse, sent_from: &str) -> Option<Pulse> {
+    fn recv(&mut self, input: Pulse, sent_from: NameId) -> Option<Pulse> {
         match self {
             Self::Broadcast(_) => Some(input),
             Self::FlipFlop(state, _) => {
@@ -58,9 +70,12 @@ impl Module {
                 }
             }
             Self::Conjunction(memory, _) => {
-                let last = memory.get_mut(sent_from).unwrap();
+                let (_, last) = memory
+                    .iter_mut()
+                    .find(|(from, _)| *from == sent_from)
+                    .unwrap();
                 *last = input;
-                if memory.values().all(|v| *v == Pulse::High) {
+                if memory.iter().all(|(_, v)| *v == Pulse::High) {
                     Some(Pulse::Low)
                 } else {
                     Some(Pulse::High)
@@ -70,7 +85,8 @@ impl Module {
     }
 }
 
-type ModulesState<'a> = HashMap<&'a str, Module>;
+// Every module, indexed by ID.  Names that are only ever outputs (like `rx`) have no module.
+type ModulesState = Vec<Option<Module>>;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 struct PulseCounts {
@@ -78,35 +94,44 @@ struct PulseCounts {
     low: u64,
 }
 
-// Simulate a button press, and return the number of
-fn run_single_loop<'a>(
-    state: &'a mut ModulesState,
+// Simulate a button press, and return the number of pulses sent, plus which modules sent a
+// High pulse to the target module.
+fn run_single_loop(
+    state: &mut ModulesState,
+    names: &Interner,
+    broadcaster: NameId,
     debug: bool,
-    target_module: &'a str,
-) -> (PulseCounts, Vec<String>) {
+    target_module: Option<NameId>,
+) -> (PulseCounts, Vec<NameId>) {
     let mut low = 0;
     let mut high = 0;
     let mut queue = VecDeque::new();
     let mut high_triggers = vec![];
 
-    queue.push_back(("".to_string(), "broadcaster".to_string(), Pulse::Low));
+    // The button isn't a module, so just pretend the broadcaster sent itself the first pulse.
+    queue.push_back((broadcaster, broadcaster, Pulse::Low));
     while let Some((sender, target, pulse)) = queue.pop_front() {
         if pulse == Pulse::High {
             high += 1
         } else {
             low += 1
         }
-        if let Some(module) = state.get_mut(target.as_str()) {
-            let output = module.recv(pulse, &sender);
+        if let Some(module) = state[target].as_mut() {
+            let output = module.recv(pulse, sender);
             if let Some(new_pulse) = output {
-                for d in module.outputs() {
+                for &d in module.outputs() {
                     if debug {
-                        println!("Adding output: {target} {:?} -> {d}", new_pulse);
+                        println!(
+                            "Adding output: {} {:?} -> {}",
+                            names.name(target),
+                            new_pulse,
+                            names.name(d)
+                        );
                     }
-                    if d == target_module && new_pulse == Pulse::High {
-                        high_triggers.push(target.clone());
+                    if Some(d) == target_module && new_pulse == Pulse::High {
+                        high_triggers.push(target);
                     }
-                    queue.push_back((target.clone(), d.to_string(), new_pulse));
+                    queue.push_back((target, d, new_pulse));
                 }
             }
         }
@@ -115,61 +140,69 @@ fn run_single_loop<'a>(
     (PulseCounts { high, low }, high_triggers)
 }
 
-pub fn run(input_path: String) {
+pub fn run(input_path: String) -> Result<(), ParseError> {
     let input = std::fs::read_to_string(input_path).unwrap();
-    let mut input_map = HashMap::new();
-    let mut modules = input
+    let mut names = Interner::new();
+    let parsed = Span::new(&input)
         .lines()
-        .map(|s| {
-            let (name, module) = Module::parse(s);
-            for d in module.outputs() {
-                input_map.entry(d.to_string()).or_insert(vec![]).push(name)
-            }
-            (name, module)
-        })
-        .collect::<HashMap<_, _>>();
+        .map(|s| Module::parse(s, &mut names))
+        .collect::<ParseResult<Vec<_>>>()?;
+
+    // Now we know every name, lay the modules out by ID, and note down the inputs to each.
+    let mut modules: ModulesState = vec![None; names.len()];
+    let mut input_map = vec![vec![]; names.len()];
+    for (id, module) in parsed {
+        for &d in module.outputs() {
+            input_map[d].push(id);
+        }
+        modules[id] = Some(module);
+    }
 
     // Initialize all the conjunction modules.
-    for (dest, inputs) in &input_map {
-        if let Some(module) = modules.get_mut(dest.as_str()) {
-            if let Module::Conjunction(state, _) = module {
-                for i in inputs {
-                    state.insert(i.to_string(), Pulse::Low);
-                }
+    for (dest, inputs) in input_map.iter().enumerate() {
+        if let Some(Module::Conjunction(state, _)) = modules[dest].as_mut() {
+            for &i in inputs {
+                state.push((i, Pulse::Low));
             }
         }
     }
 
-    // let start_state = modules.clone();
+    let lookup = |name: &str| {
+        names
+            .get(name)
+            .ok_or_else(|| Span::new(&input).error(format!("No module called {name}")))
+    };
+    let broadcaster = lookup("broadcaster")?;
+
     let mut loops = 0;
     let mut answer = PulseCounts { high: 0, low: 0 };
 
     while loops < 1000 {
-        let (new, _) = run_single_loop(&mut modules, false, "");
+        let (new, _) = run_single_loop(&mut modules, &names, broadcaster, false, None);
         answer.high += new.high;
         answer.low += new.low;
         loops += 1;
     }
 
     println!("Part 1: {}", answer.high * answer.low);
-    // modules = start_state;
 
-    let rx_inputs = input_map.get("rx").unwrap();
+    let rx_inputs = &input_map[lookup("rx")?];
     assert_eq!(rx_inputs.len(), 1);
     let rx_input = rx_inputs[0];
-    let target_inputs = input_map.get(rx_input).unwrap();
+    let target_inputs = &input_map[rx_input];
     let mut counters = HashMap::with_capacity(target_inputs.len());
 
     while counters.len() < target_inputs.len() {
         loops += 1;
-        let (_, high_triggers) = run_single_loop(&mut modules, false, &rx_input);
+        let (_, high_triggers) =
+            run_single_loop(&mut modules, &names, broadcaster, false, Some(rx_input));
         for &name in target_inputs {
             // This module sends to the aggregator that sends to rx.
             //   name -> rx_input -> rx
             // To trigger low to rx, we need to send High from all modules to rx_input.
-            for t in &high_triggers {
+            for &t in &high_triggers {
                 if t == name {
-                    let this_count = counters.entry(name.to_string()).or_insert(0u64);
+                    let this_count = counters.entry(name).or_insert(0u64);
                     *this_count = loops;
                 }
             }
@@ -179,4 +212,5 @@ pub fn run(input_path: String) {
         .values()
         .fold(1, |acc, val| num::integer::lcm(acc, *val));
     println!("Part 2: {}", part2);
+    Ok(())
 }
@@ -1,6 +1,29 @@
 // More pathfinding.
+// Part 2 took a few attempts.  Walking 26 million steps over the infinite grid is out, but
+// the number of reachable spots settles into a pattern: once the frontier has found its
+// shape it's a quadratic in the step count, plus some wobble that repeats as the edges of
+// the reachable area cross from one copy of the map into the next.
+//
+// Any sequence like that satisfies a linear recurrence, and Berlekamp-Massey finds the
+// shortest recurrence that fits a sequence.  So walk a while, fit a recurrence to the first
+// two thirds of the counts, and only trust it if it predicts the last third too.  Then jump
+// straight to the step count we want.  If nothing fits after walking `MAX_WALK` steps,
+// that's an error rather than a guess.
+//
+// The real input is kind (square, start in the middle, open row and column through the start
+// and round the edge) so a plain quadratic every 131 steps works, but none of that is
+// assumed here.  Rectangular maps, off-centre starts and blocked borders just need a longer
+// recurrence - which for a map whose sides have no common factor can be about as long as
+// the product of the sides, so big maps like that run out of walk.
+//
+// `--steps=500..510` (or just `--steps=500`) prints the count for each of those step
+// counts too.
 
-use std::collections::{HashMap, VecDeque};
+use std::collections::HashSet;
+use std::hash::{BuildHasherDefault, Hasher};
+use std::ops::Range;
+
+use crate::opts;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Space {
@@ -15,87 +38,441 @@ struct Grid {
     start: (i64, i64),
 }
 
-fn generate_distances(grid: &Grid) -> HashMap<(i64, i64), u64> {
-    let max_y = grid.grid.len();
-    let max_x = grid.grid[0].len();
-    assert_eq!(max_x, max_y); // By inspection of input.
-
-    // Start at "start"
-    let mut visited = HashMap::new();
-    let mut queue = VecDeque::new();
-
-    queue.push_back((0, grid.start));
-    visited.insert(grid.start, 0);
-    while let Some((steps, (x, y))) = queue.pop_front() {
-        // Add neighbours
-        let neighs = [((x - 1, y)), ((x, y - 1)), ((x + 1, y)), ((x, y + 1))];
-        for (nx, ny) in neighs {
-            let nx = (nx as usize % max_x) as i64;
-            let ny = (ny as usize % max_y) as i64;
-            if !visited.contains_key(&(nx, ny))
-                && grid.grid[ny as usize][nx as usize] == Space::Open
-            {
-                visited.insert((nx, ny), steps + 1);
-                queue.push_back((steps + 1, (nx, ny)));
-            }
+impl Grid {
+    fn parse(input: &str) -> Self {
+        let mut start = None;
+        let grid = input
+            .lines()
+            .enumerate()
+            .map(|(y, l)| {
+                l.chars()
+                    .enumerate()
+                    .map(|(x, c)| {
+                        if c == 'S' {
+                            start = Some((x as i64, y as i64))
+                        }
+                        match c {
+                            '#' => Space::Rock,
+                            '.' | 'S' => Space::Open,
+                            _ => panic!("Bad input"),
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        Grid {
+            grid,
+            start: start.unwrap(),
         }
     }
-    visited
+
+    fn width(&self) -> i64 {
+        self.grid[0].len() as i64
+    }
+
+    fn height(&self) -> i64 {
+        self.grid.len() as i64
+    }
+
+    // Can we step here?  Off the edge of the map is either another copy of the map, or
+    // out of bounds if we're not tiling.
+    fn is_open(&self, (x, y): (i64, i64), tiled: bool) -> bool {
+        let (x, y) = if tiled {
+            // Euclidean remainder, so negative coordinates wrap round correctly.
+            (x.rem_euclid(self.width()), y.rem_euclid(self.height()))
+        } else if (0..self.width()).contains(&x) && (0..self.height()).contains(&y) {
+            (x, y)
+        } else {
+            return false;
+        };
+        self.grid[y as usize][x as usize] == Space::Open
+    }
 }
 
-pub fn run(input_path: String) {
-    let input = std::fs::read_to_string(input_path).unwrap();
-    let mut start = None;
-    let grid = input
-        .lines()
-        .enumerate()
-        .map(|(y, l)| {
-            l.chars()
-                .enumerate()
-                .map(|(x, c)| {
-                    if c == 'S' {
-                        start = Some((x as i64, y as i64))
-                    }
-                    match c {
-                        '#' => Space::Rock,
-                        '.' | 'S' => Space::Open,
-                        _ => panic!("Bad input"),
+// The standard hasher is built to resist malicious keys, which makes it slow for the
+// hundreds of millions of points a long walk looks up.  This is the multiply-and-rotate
+// hash rustc uses internally, which is plenty for coordinates.
+#[derive(Default)]
+struct PointHasher(u64);
+
+impl Hasher for PointHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.write_u64(b as u64);
+        }
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.0 = (self.0.rotate_left(5) ^ n).wrapping_mul(0x517c_c1b7_2722_0a95);
+    }
+
+    fn write_i64(&mut self, n: i64) {
+        self.write_u64(n as u64);
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+type PointSet = HashSet<(i64, i64), BuildHasherDefault<PointHasher>>;
+
+// Breadth-first search out from the start, a layer at a time, counting how many spots are
+// first reached at each step.
+// The grid is a chessboard - every step changes the colour of square we're on - so
+// anything next to the current layer is either in the layer before or the next one.  So we
+// only ever need to keep two layers, however far we go.
+struct Walk<'a> {
+    grid: &'a Grid,
+    tiled: bool,
+    previous: PointSet,
+    frontier: PointSet,
+    first_reached: Vec<u64>,
+}
+
+impl<'a> Walk<'a> {
+    fn new(grid: &'a Grid, tiled: bool) -> Self {
+        Walk {
+            grid,
+            tiled,
+            previous: PointSet::default(),
+            frontier: PointSet::from_iter([grid.start]),
+            first_reached: vec![1],
+        }
+    }
+
+    // Carry on walking until we've done `max_steps` steps.
+    fn walk_to(&mut self, max_steps: u64) {
+        while self.first_reached.len() as u64 <= max_steps {
+            let mut next = PointSet::default();
+            for &(x, y) in &self.frontier {
+                for n in [(x - 1, y), (x, y - 1), (x + 1, y), (x, y + 1)] {
+                    if self.grid.is_open(n, self.tiled) && !self.previous.contains(&n) {
+                        next.insert(n);
                     }
-                })
-                .collect()
-        })
-        .collect();
-    let grid = Grid {
-        grid,
-        start: start.unwrap(),
+                }
+            }
+            self.first_reached.push(next.len() as u64);
+            self.previous = std::mem::replace(&mut self.frontier, next);
+        }
+    }
+
+    // How many spots can we be on after exactly so many steps, for each step count walked
+    // so far?  That's everywhere first reached in the same parity of steps or fewer, since we
+    // can always step back and forth.
+    fn reachable_counts(&self) -> Vec<u64> {
+        let mut totals = [0, 0];
+        self.first_reached
+            .iter()
+            .enumerate()
+            .map(|(step, count)| {
+                totals[step % 2] += count;
+                totals[step % 2]
+            })
+            .collect()
+    }
+}
+
+// The recurrence is found and used modulo this prime, so nothing overflows however far we
+// extrapolate.  The count after n steps is at most (n + 1)^2, so as long as that's smaller
+// than this, the remainder is the count itself.
+const MODULUS: u64 = (1 << 61) - 1;
+
+fn add_mod(a: u64, b: u64) -> u64 {
+    (a + b) % MODULUS
+}
+
+fn sub_mod(a: u64, b: u64) -> u64 {
+    (a + MODULUS - b) % MODULUS
+}
+
+fn mul_mod(a: u64, b: u64) -> u64 {
+    let product = a as u128 * b as u128;
+    // 2^61 is 1 modulo 2^61 - 1, so the high bits just get added back on.
+    ((product & MODULUS as u128) as u64 + (product >> 61) as u64) % MODULUS
+}
+
+fn inverse_mod(a: u64) -> u64 {
+    // Fermat: a^(p-2) is the inverse of a.
+    let (mut base, mut exp, mut result) = (a, MODULUS - 2, 1);
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mul_mod(result, base);
+        }
+        base = mul_mod(base, base);
+        exp >>= 1;
+    }
+    result
+}
+
+// Berlekamp-Massey: the shortest recurrence s[n] = c[0] s[n-1] + c[1] s[n-2] + ... that
+// the whole sequence satisfies.  Returns the c's.
+fn find_recurrence(seq: &[u64]) -> Vec<u64> {
+    // Both kept as 1 - c[0] x - c[1] x^2 - ..., the current best and the one before the
+    // last time the length changed.
+    let mut current = vec![1];
+    let mut previous = vec![1];
+    let mut length = 0;
+    let mut previous_discrepancy = 1;
+    let mut shift = 1;
+    for n in 0..seq.len() {
+        // How far off the current recurrence is for this term.
+        let discrepancy = current
+            .iter()
+            .zip(seq[..=n].iter().rev())
+            .fold(0, |total, (&c, &s)| add_mod(total, mul_mod(c, s)));
+        if discrepancy == 0 {
+            shift += 1;
+            continue;
+        }
+
+        // Correct it using the previous recurrence, shifted along to this term.
+        let scale = mul_mod(discrepancy, inverse_mod(previous_discrepancy));
+        let before = current.clone();
+        if current.len() < previous.len() + shift {
+            current.resize(previous.len() + shift, 0);
+        }
+        for (i, &p) in previous.iter().enumerate() {
+            current[i + shift] = sub_mod(current[i + shift], mul_mod(scale, p));
+        }
+        if 2 * length <= n {
+            length = n + 1 - length;
+            previous = before;
+            previous_discrepancy = discrepancy;
+            shift = 1;
+        } else {
+            shift += 1;
+        }
+    }
+    current.resize(length + 1, 0);
+    current[1..].iter().map(|&c| sub_mod(0, c)).collect()
+}
+
+fn recurrence_holds(recurrence: &[u64], seq: &[u64]) -> bool {
+    seq.windows(recurrence.len() + 1).all(|terms| {
+        let (&last, earlier) = terms.split_last().unwrap();
+        let predicted = recurrence
+            .iter()
+            .zip(earlier.iter().rev())
+            .fold(0, |total, (&c, &s)| add_mod(total, mul_mod(c, s)));
+        predicted == last % MODULUS
+    })
+}
+
+// The nth term of a sequence following the recurrence, given at least its first few terms.
+// Every term is a combination of the first k, and for term n the combination is x^n reduced
+// using x^k = c[0] x^(k-1) + ... + c[k-1].  So work out x^n by repeated squaring.
+fn nth_term(recurrence: &[u64], seq: &[u64], n: u64) -> u64 {
+    let k = recurrence.len();
+    if k == 0 {
+        return 0;
+    }
+    // Fold any powers of x^k and above back down.
+    let reduce = |mut poly: Vec<u64>| {
+        for i in (k..poly.len()).rev() {
+            let top = poly[i];
+            for (j, &c) in recurrence.iter().enumerate() {
+                poly[i - 1 - j] = add_mod(poly[i - 1 - j], mul_mod(top, c));
+            }
+        }
+        poly.truncate(k);
+        poly
     };
 
-    // Observe that:
-    // - if we can get to a position in N steps we can get there in every other multiple of 2 below N.
-    // - the provided map has no rocks on the outer boundary.
+    let mut power = vec![0; k];
+    power[0] = 1;
+    for bit in (0..u64::BITS - n.leading_zeros()).rev() {
+        let mut square = vec![0; 2 * k - 1];
+        for (i, &a) in power.iter().enumerate().filter(|(_, &a)| a != 0) {
+            for (j, &b) in power.iter().enumerate() {
+                square[i + j] = add_mod(square[i + j], mul_mod(a, b));
+            }
+        }
+        power = reduce(square);
+        if (n >> bit) & 1 == 1 {
+            // Multiply by x.
+            power.insert(0, 0);
+            power = reduce(power);
+        }
+    }
+    power
+        .iter()
+        .zip(seq)
+        .fold(0, |total, (&p, &s)| add_mod(total, mul_mod(p, s % MODULUS)))
+}
+
+// How far to walk before looking for a recurrence, and the furthest we'll go.
+const INITIAL_WALK: u64 = 256;
+const MAX_WALK: u64 = 8192;
+
+// Reachable counts over the infinite grid: walked for real as far as we went, and predicted
+// by a recurrence beyond that.
+struct TiledCounts {
+    counts: Vec<u64>,
+    recurrence: Vec<u64>,
+}
+
+impl TiledCounts {
+    fn new(grid: &Grid) -> Result<Self, String> {
+        let mut walk = Walk::new(grid, true);
+        let mut max_steps = INITIAL_WALK;
+        loop {
+            walk.walk_to(max_steps);
+            let counts = walk.reachable_counts();
+            // Fit the first two thirds, with enough terms to pin the recurrence down, and
+            // check it against the rest.
+            let fitted = counts.len() * 2 / 3;
+            let recurrence = find_recurrence(&counts[..fitted]);
+            if 2 * recurrence.len() < fitted && recurrence_holds(&recurrence, &counts) {
+                return Ok(TiledCounts { counts, recurrence });
+            }
+            if max_steps >= MAX_WALK {
+                return Err(format!(
+                    "Reachable counts didn't settle into a pattern within {MAX_WALK} steps"
+                ));
+            }
+            // The walk carries on from where it got to, so this only costs the extra steps.
+            max_steps = (max_steps * 3 / 2).min(MAX_WALK);
+        }
+    }
+
+    fn reachable_after(&self, steps: u64) -> Result<u64, String> {
+        if let Some(&count) = self.counts.get(steps as usize) {
+            return Ok(count);
+        }
+        if (steps as u128 + 1).pow(2) >= MODULUS as u128 {
+            return Err(format!("Can't extrapolate as far as {steps} steps"));
+        }
+        Ok(nth_term(&self.recurrence, &self.counts, steps))
+    }
+}
+
+// `500..510`, or a single step count.
+fn parse_steps(text: &str) -> Option<Range<u64>> {
+    match text.split_once("..") {
+        Some((start, end)) => Some(start.parse().ok()?..end.parse().ok()?),
+        None => {
+            let steps = text.parse().ok()?;
+            Some(steps..steps + 1)
+        }
+    }
+}
+
+pub fn run(input_path: String) -> Result<(), String> {
+    let input = std::fs::read_to_string(input_path).unwrap();
+    let grid = Grid::parse(&input);
+
+    // Observe that if we can get to a position in N steps we can get there in every other
+    // multiple of 2 below N.
     //
     // So we can start by breadth-first searching every position on the grid and storing its
     // distance from the start.  If that disatnce is at most 64 and of even parity, we can reach it.
-    let distances = generate_distances(&grid);
-    let part1 = distances
-        .values()
-        .filter(|&&v| v <= 64 && v % 2 == 0)
-        .count();
+    // Part 1 is only on the one copy of the map.
+    let mut walk = Walk::new(&grid, false);
+    walk.walk_to(64);
+    let part1 = walk.reachable_counts()[64];
     println!("Part 1: {}", part1);
 
-    // For part2, we can't keep iterating.
-    // Note that because the grid has open edges, if we need to cross more than 1 grid instance, we can always do so
-    // in the Manhattan distance.
-    // So, we can reach:
-    // - Anywhere in the existing grid, multiplied by that is (2N - 1) * N, where N is (max_steps / grid size)
-    // - Then for remaining spaces, we can reach anywhere that is
-    let maxsteps_p2 = 26501365u64;
-    let grid_repeats = maxsteps_p2 / grid.grid.len() as u64;
-    let grid_max_size = 2 * grid_repeats - 1;
-    let local_reachable = distances.values().filter(|&&v| v <= maxsteps_p2 && v % 2 == 1).count();
-    dbg!(local_reachable, grid_max_size);
-    let part2 = local_reachable as u64 * grid_max_size * grid_max_size; //max_positions_for_stepcount(&grid, 26501365);
-    // Now need to add on all those reachable spots on the most distant grid.
-
+    // For part2, we can't keep iterating over every step, but we can extrapolate.
+    let counts = TiledCounts::new(&grid)?;
+    if let Some(text) = opts::value("steps") {
+        let steps = parse_steps(&text).ok_or(format!("Bad step range {text}"))?;
+        for steps in steps {
+            println!("{steps} steps: {}", counts.reachable_after(steps)?);
+        }
+    }
+    let part2 = counts.reachable_after(26501365)?;
     println!("Part 2: {}", part2);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+...........
+.....###.#.
+.###.##..#.
+..#.#...#..
+....#.#....
+.##..S####.
+.##..#...#.
+.......##..
+.##.#.####.
+.##..##.##.
+...........
+";
+
+    // Check every step count up to a few hundred past where we stopped walking against
+    // actually walking that far.
+    fn check_against_brute_force(grid: &Grid) {
+        let counts = TiledCounts::new(grid).unwrap();
+        let max_steps = counts.counts.len() as u64 + 300;
+        let mut walk = Walk::new(grid, true);
+        walk.walk_to(max_steps);
+        for (steps, &expected) in walk.reachable_counts().iter().enumerate() {
+            assert_eq!(
+                counts.reachable_after(steps as u64),
+                Ok(expected),
+                "{steps} steps"
+            );
+        }
+    }
+
+    #[test]
+    fn example_part1() {
+        let grid = Grid::parse(EXAMPLE);
+        let mut walk = Walk::new(&grid, false);
+        walk.walk_to(6);
+        assert_eq!(walk.reachable_counts()[6], 16);
+    }
+
+    #[test]
+    fn example_part2() {
+        let counts = TiledCounts::new(&Grid::parse(EXAMPLE)).unwrap();
+        for (steps, expected) in [
+            (6, 16),
+            (10, 50),
+            (50, 1594),
+            (100, 6536),
+            (500, 167004),
+            (1000, 668697),
+            (5000, 16733044),
+        ] {
+            assert_eq!(counts.reachable_after(steps), Ok(expected), "{steps} steps");
+        }
+    }
+
+    #[test]
+    fn example_matches_brute_force() {
+        check_against_brute_force(&Grid::parse(EXAMPLE));
+    }
+
+    // Rectangular, start off-centre, and the border partly blocked.
+    #[test]
+    fn blocked_border_matches_brute_force() {
+        let grid = Grid::parse(".#####.\n#....##\n#..S...\n..#..#.\n#...#..\n");
+        check_against_brute_force(&grid);
+        let counts = TiledCounts::new(&grid).unwrap();
+        assert_eq!(counts.reachable_after(713), Ok(1745));
+        assert_eq!(counts.reachable_after(739), Ok(1808));
+    }
+
+    #[test]
+    fn non_square_matches_brute_force() {
+        let grid = Grid::parse(
+            "\
+..#......#...
+.#...##....#.
+...#....#....
+S.....#...#..
+..##.....#..#
+.....#.......
+#..#...##..#.
+....#........
+",
+        );
+        check_against_brute_force(&grid);
+    }
 }
@@ -1,12 +1,162 @@
-// Template.
-// Copy to daynum.rs, and uncomment relevant lins in main to add
+// Falling bricks.
+// Settling is easy enough if you drop the bricks in height order, keeping a height map of the
+// top of the pile (and which brick is there) for each (x, y) column.  As each brick lands we
+// note which bricks it's resting on, which gives the supports graph for free.
+//
+// Part 1 is then just counting bricks where everything they hold up has another support.
+//
+// Part 2 could re-run the chain reaction for every brick, but there's a neater way.  Treat the
+// ground as the root of a graph with edges from each supporter to the bricks it holds up.  A
+// brick falls when X is removed exactly when every route from the ground to it goes via X - ie
+// X *dominates* it.  The bricks settle in an order where supporters always come first, so each
+// brick's immediate dominator is just the common ancestor (in the dominator tree) of all its
+// supporters, and the answer is the sum of the dominator subtree sizes.
 
-pub fn run(input_path: String) {
+use crate::parse::{ParseError, ParseResult, Span};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Brick {
+    start: (usize, usize, usize),
+    end: (usize, usize, usize),
+}
+
+impl Brick {
+    fn parse(line: Span) -> ParseResult<Self> {
+        let [x1, y1, z1, x2, y2, z2] = line.captures("{},{},{}~{},{},{}")?;
+        let start: (usize, usize, usize) = (x1.parse()?, y1.parse()?, z1.parse()?);
+        let end: (usize, usize, usize) = (x2.parse()?, y2.parse()?, z2.parse()?);
+        // Makes the rest easier if the ends are in order.
+        Ok(Brick {
+            start: (start.0.min(end.0), start.1.min(end.1), start.2.min(end.2)),
+            end: (start.0.max(end.0), start.1.max(end.1), start.2.max(end.2)),
+        })
+    }
+
+    // The (x, y) columns the brick covers.
+    fn footprint(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (self.start.0..=self.end.0)
+            .flat_map(move |x| (self.start.1..=self.end.1).map(move |y| (x, y)))
+    }
+
+    fn height(&self) -> usize {
+        self.end.2 - self.start.2 + 1
+    }
+}
+
+// The settled pile: for each brick (in settled order), which bricks it rests on and which
+// rest on it.  An empty `supported_by` means it's on the ground.
+struct Pile {
+    supported_by: Vec<Vec<usize>>,
+    supports: Vec<Vec<usize>>,
+}
+
+// Drop all the bricks.  Assumes they're sorted by lowest point.
+fn settle(bricks: &[Brick]) -> Pile {
+    let max_x = bricks.iter().map(|b| b.end.0).max().unwrap_or(0);
+    let max_y = bricks.iter().map(|b| b.end.1).max().unwrap_or(0);
+    // Top of the pile in each column: height and the brick there.
+    let mut heights: Vec<Vec<(usize, Option<usize>)>> = vec![vec![(0, None); max_y + 1]; max_x + 1];
+    let mut supported_by = vec![vec![]; bricks.len()];
+    let mut supports = vec![vec![]; bricks.len()];
+
+    for (id, brick) in bricks.iter().enumerate() {
+        // It lands on the highest point underneath it, resting on whichever bricks are there.
+        let rest_height = brick
+            .footprint()
+            .map(|(x, y)| heights[x][y].0)
+            .max()
+            .unwrap();
+        let mut below = brick
+            .footprint()
+            .filter_map(|(x, y)| match heights[x][y] {
+                (h, Some(other)) if h == rest_height => Some(other),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        below.sort();
+        below.dedup();
+
+        for &other in &below {
+            supports[other].push(id);
+        }
+        supported_by[id] = below;
+
+        let top = rest_height + brick.height();
+        for (x, y) in brick.footprint() {
+            heights[x][y] = (top, Some(id));
+        }
+    }
+
+    Pile {
+        supported_by,
+        supports,
+    }
+}
+
+// Count the bricks that would fall if each brick were removed, summed over all bricks.
+fn count_chain_reactions(pile: &Pile) -> usize {
+    let count = pile.supported_by.len();
+    // Use an extra node for the ground, as the root of the dominator tree.
+    let ground = count;
+    let mut idom = vec![ground; count + 1];
+    let mut depth = vec![0; count + 1];
+
+    // Bricks are in settled order, so all a brick's supporters have their dominators already.
+    for id in 0..count {
+        let mut supporters = pile.supported_by[id].iter().copied();
+        let Some(first) = supporters.next() else {
+            // On the ground, so can't fall.
+            depth[id] = 1;
+            continue;
+        };
+        let dom = supporters.fold(first, |mut a, mut b| {
+            // Walk both up the tree until they meet.
+            while a != b {
+                if depth[a] >= depth[b] {
+                    a = idom[a];
+                } else {
+                    b = idom[b];
+                }
+            }
+            a
+        });
+        idom[id] = dom;
+        depth[id] = depth[dom] + 1;
+    }
+
+    // Size of each subtree of the dominator tree.  Children always come after their parents,
+    // so sum up backwards.
+    let mut subtree = vec![1; count + 1];
+    for id in (0..count).rev() {
+        subtree[idom[id]] += subtree[id];
+    }
+    // Everything in the subtree apart from the brick itself falls.
+    subtree[..count].iter().map(|size| size - 1).sum()
+}
+
+pub fn run(input_path: String) -> Result<(), ParseError> {
     let input = std::fs::read_to_string(input_path).unwrap();
+    let mut bricks = Span::new(&input)
+        .lines()
+        .map(Brick::parse)
+        .collect::<ParseResult<Vec<_>>>()?;
+    bricks.sort_by_key(|b| b.start.2);
+
+    let pile = settle(&bricks);
 
-    let part1 = 0;
-    let part2 = 0;
+    // A brick is safe to remove if everything it supports has another support.
+    let part1 = pile
+        .supports
+        .iter()
+        .filter(|above| {
+            above
+                .iter()
+                .all(|&other| pile.supported_by[other].len() > 1)
+        })
+        .count();
+    let part2 = count_chain_reactions(&pile);
 
     println!("Part 1: {}", part1);
     println!("Part 2: {}", part2);
-}
\ No newline at end of file
+    Ok(())
+}
@@ -1,12 +1,198 @@
-// Template.
-// Copy to daynum.rs, and uncomment relevant lins in main to add
+// Longest path is NP-hard in general, so the trick is to make the graph small.
+// The trails are almost all long corridors, with only a few dozen junctions where there's a
+// real choice.  So compress the grid down to a graph of junctions, with edges weighted by the
+// corridor length between them, and brute-force that with a depth-first search.
+//
+// With so few junctions, the visited set fits in a u64 bitmask, and a couple of prunes keep
+// the search quick:
+// - Once we're at the only junction next to the exit, we have to take the exit - going
+//   anywhere else means we can never get back to it.
+// - Give up on any route that couldn't beat the best so far even if it could use the longest
+//   edge out of every junction it hasn't visited yet.
+
+use std::collections::HashMap;
+
+type Pos = (usize, usize);
+
+// Directions, along with the slope you can go down in that direction.
+const DIRS: [(isize, isize, char); 4] = [(0, -1, '^'), (1, 0, '>'), (0, 1, 'v'), (-1, 0, '<')];
+
+struct Grid {
+    grid: Vec<Vec<char>>,
+    start: Pos,
+    end: Pos,
+}
+
+impl Grid {
+    fn from_str(input: &str) -> Self {
+        let grid = input
+            .lines()
+            .map(|l| l.chars().collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        // The start and end are the only gaps in the top and bottom rows.
+        let start = (grid[0].iter().position(|&c| c == '.').unwrap(), 0);
+        let last = grid.len() - 1;
+        let end = (grid[last].iter().position(|&c| c == '.').unwrap(), last);
+        Grid { grid, start, end }
+    }
+
+    // Step in a direction, if that's somewhere we can stand.
+    fn step(&self, (x, y): Pos, (dx, dy): (isize, isize)) -> Option<Pos> {
+        let nx = x.checked_add_signed(dx)?;
+        let ny = y.checked_add_signed(dy)?;
+        match self.grid.get(ny)?.get(nx)? {
+            '#' => None,
+            _ => Some((nx, ny)),
+        }
+    }
+
+    fn is_junction(&self, pos: Pos) -> bool {
+        pos == self.start
+            || pos == self.end
+            || DIRS
+                .iter()
+                .filter(|(dx, dy, _)| self.step(pos, (*dx, *dy)).is_some())
+                .count()
+                > 2
+    }
+}
+
+// The compressed graph: for each junction, the (junction, distance) pairs reachable from it.
+struct Graph {
+    edges: Vec<Vec<(usize, u32)>>,
+    start: usize,
+    end: usize,
+}
+
+impl Graph {
+    fn build(grid: &Grid, slippery: bool) -> Self {
+        let mut junctions = HashMap::new();
+        for (y, row) in grid.grid.iter().enumerate() {
+            for (x, &c) in row.iter().enumerate() {
+                if c != '#' && grid.is_junction((x, y)) {
+                    let id = junctions.len();
+                    junctions.insert((x, y), id);
+                }
+            }
+        }
+        assert!(
+            junctions.len() <= 64,
+            "Too many junctions for a u64 bitmask"
+        );
+
+        let mut edges = vec![vec![]; junctions.len()];
+        for (&from, &from_id) in &junctions {
+            // Follow each corridor out of the junction until we hit the next one.
+            for &(dx, dy, slope) in &DIRS {
+                let mut prev = from;
+                let Some(mut pos) = grid.step(from, (dx, dy)) else {
+                    continue;
+                };
+                let mut dist = 1;
+                let c = grid.grid[pos.1][pos.0];
+                // Can't walk up a slope.
+                let mut blocked = slippery && c != '.' && c != slope;
+                while !blocked && !junctions.contains_key(&pos) {
+                    // Corridor, so exactly one way on that isn't back where we came from.
+                    let next = DIRS
+                        .iter()
+                        .filter_map(|&(dx, dy, slope)| grid.step(pos, (dx, dy)).map(|n| (n, slope)))
+                        .find(|(n, _)| *n != prev);
+                    let Some((next, slope)) = next else {
+                        // Dead end.
+                        blocked = true;
+                        break;
+                    };
+                    let c = grid.grid[next.1][next.0];
+                    blocked = slippery && c != '.' && c != slope;
+                    prev = pos;
+                    pos = next;
+                    dist += 1;
+                }
+                if !blocked {
+                    edges[from_id].push((junctions[&pos], dist));
+                }
+            }
+        }
+
+        Graph {
+            edges,
+            start: junctions[&grid.start],
+            end: junctions[&grid.end],
+        }
+    }
+
+    fn longest_path(&self) -> u32 {
+        // If the exit only has one way in, that junction has to go straight to the exit.
+        let incoming = (0..self.edges.len())
+            .filter(|&from| self.edges[from].iter().any(|&(to, _)| to == self.end))
+            .collect::<Vec<_>>();
+        let exit_gate = match incoming[..] {
+            [gate] => Some(gate),
+            _ => None,
+        };
+        // The most each junction could add to a route.
+        let best_edges = self
+            .edges
+            .iter()
+            .map(|e| e.iter().map(|&(_, d)| d).max().unwrap_or(0))
+            .collect::<Vec<_>>();
+        let remaining = best_edges.iter().sum();
+
+        let mut search = Search {
+            graph: self,
+            exit_gate,
+            best_edges,
+            best: None,
+        };
+        search.dfs(self.start, 1 << self.start, 0, remaining);
+        search.best.expect("No route to the exit")
+    }
+}
+
+// State for the depth-first search over the junctions.
+struct Search<'a> {
+    graph: &'a Graph,
+    exit_gate: Option<usize>,
+    best_edges: Vec<u32>,
+    best: Option<u32>,
+}
+
+impl Search<'_> {
+    // Carry on from `node`, having come `dist` so far via the `visited` junctions.
+    // `remaining` is the sum of the longest edge out of every junction we could still use.
+    fn dfs(&mut self, node: usize, visited: u64, dist: u32, remaining: u32) {
+        if node == self.graph.end {
+            self.best = Some(self.best.map_or(dist, |b| b.max(dist)));
+            return;
+        }
+        // Even taking the longest way out of every junction left can't beat what we have.
+        if self.best.is_some_and(|b| dist + remaining <= b) {
+            return;
+        }
+        // We've used up this junction's potential.
+        let remaining = remaining - self.best_edges[node];
+        for &(next, len) in &self.graph.edges[node] {
+            if visited & (1 << next) != 0 {
+                continue;
+            }
+            if Some(node) == self.exit_gate && next != self.graph.end {
+                continue;
+            }
+            self.dfs(next, visited | (1 << next), dist + len, remaining);
+        }
+    }
+}
 
 pub fn run(input_path: String) {
     let input = std::fs::read_to_string(input_path).unwrap();
+    let grid = Grid::from_str(&input);
 
-    let part1 = 0;
-    let part2 = 0;
+    // Part 1 - can only go down slopes.
+    let part1 = Graph::build(&grid, true).longest_path();
+    // Part 2 - slopes are just paths.
+    let part2 = Graph::build(&grid, false).longest_path();
 
     println!("Part 1: {}", part1);
     println!("Part 2: {}", part2);
-}
\ No newline at end of file
+}
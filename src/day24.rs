@@ -1,12 +1,236 @@
-// Template.
-// Copy to daynum.rs, and uncomment relevant lins in main to add
+// Hailstones.
+// The numbers are big enough (positions ~10^14) that floats get the answers subtly wrong, so
+// everything here is exact.
+//
+// Part 1 is line intersection in 2D.  Solving for the times on each path gives a fraction
+// with the cross product of the velocities as the denominator, so rather than dividing, the
+// "is it in the future" and "is it in the test area" checks are done by multiplying the
+// bounds through by the denominator instead - all of which fits in an i128.
+// The test area defaults to the real puzzle's; `--area=7,27` sets it (eg for the example).
+//
+// Part 2 looks non-linear at first: the rock at P with velocity V hits hailstone i at time
+// t_i when P + t_i V = p_i + t_i v_i.  That means (P - p_i) and (V - v_i) are parallel, so
+//   (P - p_i) x (V - v_i) = 0
+//   P x V - P x v_i - p_i x V + p_i x v_i = 0
+// The P x V term is the same for every hailstone, so subtracting the equations for two
+// hailstones cancels it and leaves three linear equations in the six unknowns.  Two pairs of
+// hailstones (so three hailstones) is then enough to pin the rock down, and solving that with
+// Gaussian elimination over big rationals keeps it exact.
 
-pub fn run(input_path: String) {
+use crate::opts;
+use crate::parse::{ParseResult, Span};
+use num::{BigInt, BigRational, One, Zero};
+
+type Vec3 = [i64; 3];
+
+#[derive(Debug, Clone, Copy)]
+struct Hailstone {
+    pos: Vec3,
+    vel: Vec3,
+}
+
+impl Hailstone {
+    fn parse(line: Span) -> ParseResult<Self> {
+        let [pos, vel] = line.captures("{} @ {}")?;
+        let triple = |span: Span| -> ParseResult<Vec3> {
+            span.numbers::<i64>()?
+                .try_into()
+                .map_err(|_| span.error("expected 3 coordinates"))
+        };
+        Ok(Hailstone {
+            pos: triple(pos)?,
+            vel: triple(vel)?,
+        })
+    }
+}
+
+fn cross(a: Vec3, b: Vec3) -> [i128; 3] {
+    let [ax, ay, az] = a.map(i128::from);
+    let [bx, by, bz] = b.map(i128::from);
+    [ay * bz - az * by, az * bx - ax * bz, ax * by - ay * bx]
+}
+
+fn sub(a: Vec3, b: Vec3) -> Vec3 {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+// Do the paths of two hailstones cross (ignoring z) at some point in both their futures,
+// inside the given area?
+fn paths_cross_in(a: &Hailstone, b: &Hailstone, min: i128, max: i128) -> bool {
+    let [apx, apy, _] = a.pos.map(i128::from);
+    let [avx, avy, _] = a.vel.map(i128::from);
+    let [bpx, bpy, _] = b.pos.map(i128::from);
+    let [bvx, bvy, _] = b.vel.map(i128::from);
+
+    // Solve a.pos + t a.vel = b.pos + s b.vel, giving t = t_num / det and s = s_num / det.
+    let det = avx * bvy - avy * bvx;
+    if det == 0 {
+        // Parallel.  (Could be the same line, but the input doesn't have any of those.)
+        return false;
+    }
+    let (dx, dy) = (bpx - apx, bpy - apy);
+    let t_num = dx * bvy - dy * bvx;
+    let s_num = dx * avy - dy * avx;
+    // Make the denominator positive so the comparisons don't flip.
+    let (det, t_num, s_num) = if det < 0 {
+        (-det, -t_num, -s_num)
+    } else {
+        (det, t_num, s_num)
+    };
+    if t_num < 0 || s_num < 0 {
+        // Crossed in the past.
+        return false;
+    }
+    // The crossing point, scaled up by det.
+    let x = apx * det + avx * t_num;
+    let y = apy * det + avy * t_num;
+    let range = min * det..=max * det;
+    range.contains(&x) && range.contains(&y)
+}
+
+// Solve the linear system `matrix` . x = `rhs` exactly.  None if it doesn't have a unique
+// solution.
+fn solve_linear<const N: usize>(
+    matrix: [[i128; N]; N],
+    rhs: [i128; N],
+) -> Option<[BigRational; N]> {
+    let to_rational = |v: i128| BigRational::from_integer(BigInt::from(v));
+    // Augmented matrix.
+    let mut rows = (0..N)
+        .map(|r| {
+            matrix[r]
+                .iter()
+                .chain(std::iter::once(&rhs[r]))
+                .map(|&v| to_rational(v))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    for col in 0..N {
+        let pivot = (col..N).find(|&r| !rows[r][col].is_zero())?;
+        rows.swap(col, pivot);
+        let scale = BigRational::one() / &rows[col][col];
+        for v in rows[col].iter_mut() {
+            *v *= &scale;
+        }
+        let pivot_row = rows[col].clone();
+        for (r, row) in rows.iter_mut().enumerate() {
+            if r == col || row[col].is_zero() {
+                continue;
+            }
+            let factor = row[col].clone();
+            for (v, p) in row.iter_mut().zip(&pivot_row).skip(col) {
+                *v -= &factor * p;
+            }
+        }
+    }
+    Some(std::array::from_fn(|r| rows[r][N].clone()))
+}
+
+// The three equations from cancelling P x V between hailstones a and b, as rows over
+// (Px, Py, Pz, Vx, Vy, Vz):
+//   P x (vb - va) + (pb - pa) x V = pb x vb - pa x va
+fn pair_equations(a: &Hailstone, b: &Hailstone) -> ([[i128; 6]; 3], [i128; 3]) {
+    let [dvx, dvy, dvz] = sub(b.vel, a.vel).map(i128::from);
+    let [dpx, dpy, dpz] = sub(b.pos, a.pos).map(i128::from);
+    let (cb, ca) = (cross(b.pos, b.vel), cross(a.pos, a.vel));
+    let rows = [
+        [0, dvz, -dvy, 0, -dpz, dpy],
+        [-dvz, 0, dvx, dpz, 0, -dpx],
+        [dvy, -dvx, 0, -dpy, dpx, 0],
+    ];
+    (rows, [cb[0] - ca[0], cb[1] - ca[1], cb[2] - ca[2]])
+}
+
+// Find the rock's starting position, from the first three hailstones that give a solvable
+// system.  (The first three nearly always do, but e.g. two parallel ones won't.)
+fn find_rock(hail: &[Hailstone]) -> Option<[BigInt; 3]> {
+    for i in 0..hail.len() {
+        for j in i + 1..hail.len() {
+            for k in j + 1..hail.len() {
+                let (m1, r1) = pair_equations(&hail[i], &hail[j]);
+                let (m2, r2) = pair_equations(&hail[i], &hail[k]);
+                let matrix = [m1[0], m1[1], m1[2], m2[0], m2[1], m2[2]];
+                let rhs = [r1[0], r1[1], r1[2], r2[0], r2[1], r2[2]];
+                if let Some(solution) = solve_linear(matrix, rhs) {
+                    assert!(
+                        solution.iter().all(|v| v.is_integer()),
+                        "Rock doesn't start at whole-number coordinates"
+                    );
+                    return Some(std::array::from_fn(|d| solution[d].to_integer()));
+                }
+            }
+        }
+    }
+    None
+}
+
+// The test area, as `min,max` (the same on both axes).
+fn parse_area(text: &str) -> Option<(i128, i128)> {
+    let (min, max) = text.split_once(',')?;
+    let (min, max) = (min.trim().parse().ok()?, max.trim().parse().ok()?);
+    (min <= max).then_some((min, max))
+}
+
+// How many pairs of hailstones cross paths inside the test area.
+fn count_crossings(hail: &[Hailstone], min: i128, max: i128) -> usize {
+    hail.iter()
+        .enumerate()
+        .flat_map(|(idx, a)| hail[idx + 1..].iter().map(move |b| (a, b)))
+        .filter(|(a, b)| paths_cross_in(a, b, min, max))
+        .count()
+}
+
+pub fn run(input_path: String) -> Result<(), String> {
     let input = std::fs::read_to_string(input_path).unwrap();
+    let hail = Span::new(&input)
+        .lines()
+        .map(Hailstone::parse)
+        .collect::<ParseResult<Vec<_>>>()?;
+
+    let (min, max) = match opts::value("area") {
+        Some(text) => parse_area(&text).ok_or(format!("Bad test area {text}"))?,
+        None => (200000000000000, 400000000000000),
+    };
+    let part1 = count_crossings(&hail, min, max);
 
-    let part1 = 0;
-    let part2 = 0;
+    let rock = find_rock(&hail).expect("No rock throw hits every hailstone");
+    let part2 = rock.iter().sum::<BigInt>();
 
     println!("Part 1: {}", part1);
     println!("Part 2: {}", part2);
-}
\ No newline at end of file
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+19, 13, 30 @ -2,  1, -2
+18, 19, 22 @ -1, -1, -2
+20, 25, 34 @ -2, -2, -4
+12, 31, 28 @ -1, -2, -1
+20, 19, 15 @  1, -5, -3
+";
+
+    fn example() -> Vec<Hailstone> {
+        Span::new(EXAMPLE)
+            .lines()
+            .map(Hailstone::parse)
+            .collect::<ParseResult<_>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn example_part1() {
+        assert_eq!(parse_area("7,27"), Some((7, 27)));
+        assert_eq!(count_crossings(&example(), 7, 27), 2);
+    }
+
+    #[test]
+    fn example_part2() {
+        let rock = find_rock(&example()).unwrap();
+        assert_eq!(rock, [24, 13, 10].map(BigInt::from));
+    }
+}
@@ -1,12 +1,152 @@
-// Template.
-// Copy to daynum.rs, and uncomment relevant lins in main to add
+// Snowverload.
+// We're told the answer is a cut of exactly three wires, so this is a minimum cut problem.
+// Stoer-Wagner would do it, but with unit capacities max-flow is simpler and quicker: by
+// max-flow/min-cut, two components on opposite sides of the cut have a max flow of exactly 3
+// between them, while two on the same side have more (otherwise there'd be a second 3-cut).
+//
+// So pick a start, and try ends starting with the furthest away (likely to be over the cut)
+// until the flow is 3.  Augment with BFS paths (Edmonds-Karp), giving up once the flow is past
+// 3, so each attempt is at most five BFSes: four that find a path, and a last one that either
+// fails (and finds the residual side) or notices the flow is too big.  Whatever the start can
+// still reach in the residual graph is its side.
 
-pub fn run(input_path: String) {
+use std::collections::VecDeque;
+
+use crate::intern::Interner;
+use crate::opts;
+use crate::parse::{ParseError, ParseResult, Span};
+
+const CUT_SIZE: usize = 3;
+
+// The two components a wire joins.
+type Wire = (usize, usize);
+
+struct Graph {
+    names: Interner,
+    edges: Vec<Wire>,
+    // For each component, the (neighbour, edge index) pairs.
+    adjacent: Vec<Vec<(usize, usize)>>,
+}
+
+impl Graph {
+    fn parse(input: Span) -> ParseResult<Self> {
+        let mut names = Interner::new();
+        let mut edges = vec![];
+        for line in input.lines() {
+            let (from, to) = line.labelled()?;
+            let from = names.intern(from.as_str());
+            for other in to.words() {
+                edges.push((from, names.intern(other.as_str())));
+            }
+        }
+        let mut adjacent = vec![vec![]; names.len()];
+        for (idx, &(a, b)) in edges.iter().enumerate() {
+            adjacent[a].push((b, idx));
+            adjacent[b].push((a, idx));
+        }
+        Ok(Graph {
+            names,
+            edges,
+            adjacent,
+        })
+    }
+
+    // Components in order of distance from `start`.
+    fn bfs_order(&self, start: usize) -> Vec<usize> {
+        let mut seen = vec![false; self.adjacent.len()];
+        seen[start] = true;
+        let mut order = vec![start];
+        let mut idx = 0;
+        while idx < order.len() {
+            for &(next, _) in &self.adjacent[order[idx]] {
+                if !seen[next] {
+                    seen[next] = true;
+                    order.push(next);
+                }
+            }
+            idx += 1;
+        }
+        order
+    }
+
+    // Push flow from `source` to `sink` until there's no more room, or it's clear the cut
+    // between them is bigger than `limit`.  Returns the flow, and which components are on
+    // the source's side of the cut.
+    fn max_flow(&self, source: usize, sink: usize, limit: usize) -> (usize, Vec<bool>) {
+        // Flow along each wire: +1 is from the first end to the second, -1 the other way.
+        let mut flow = vec![0i8; self.edges.len()];
+        let mut total = 0;
+        loop {
+            // Find the shortest path with spare capacity.
+            let mut came_from = vec![None; self.adjacent.len()];
+            let mut reached = vec![false; self.adjacent.len()];
+            reached[source] = true;
+            let mut queue = VecDeque::from([source]);
+            while let Some(node) = queue.pop_front() {
+                if node == sink {
+                    break;
+                }
+                for &(next, edge) in &self.adjacent[node] {
+                    let forwards = self.edges[edge].0 == node;
+                    let spare = if forwards {
+                        flow[edge] < 1
+                    } else {
+                        flow[edge] > -1
+                    };
+                    if spare && !reached[next] {
+                        reached[next] = true;
+                        came_from[next] = Some((node, edge));
+                        queue.push_back(next);
+                    }
+                }
+            }
+            if !reached[sink] || total > limit {
+                return (total, reached);
+            }
+            // Send one unit of flow back along the path.
+            let mut node = sink;
+            while let Some((prev, edge)) = came_from[node] {
+                flow[edge] += if self.edges[edge].0 == prev { 1 } else { -1 };
+                node = prev;
+            }
+            total += 1;
+        }
+    }
+
+    // Split the components into the two groups joined by `CUT_SIZE` wires.  Gives the
+    // components on one side, and the wires to cut.
+    fn find_cut(&self) -> Option<(Vec<bool>, Vec<Wire>)> {
+        let order = self.bfs_order(0);
+        let source = order[0];
+        for &sink in order[1..].iter().rev() {
+            let (flow, side) = self.max_flow(source, sink, CUT_SIZE);
+            if flow == CUT_SIZE {
+                let cut = self
+                    .edges
+                    .iter()
+                    .copied()
+                    .filter(|&(a, b)| side[a] != side[b])
+                    .collect();
+                return Some((side, cut));
+            }
+        }
+        None
+    }
+}
+
+pub fn run(input_path: String) -> Result<(), ParseError> {
     let input = std::fs::read_to_string(input_path).unwrap();
+    let graph = Graph::parse(Span::new(&input))?;
 
-    let part1 = 0;
-    let part2 = 0;
+    let (side, cut) = graph.find_cut().expect("No three wire cut");
+    if opts::verbose() {
+        for (a, b) in cut {
+            println!("Cut {}/{}", graph.names.name(a), graph.names.name(b));
+        }
+    }
+    let group = side.iter().filter(|&&s| s).count();
+    let part1 = group * (side.len() - group);
 
     println!("Part 1: {}", part1);
-    println!("Part 2: {}", part2);
-}
\ No newline at end of file
+    Ok(())
+}
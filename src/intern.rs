@@ -0,0 +1,50 @@
+// String interning.
+// Several days name their nodes/modules/workflows with short strings and then spend their
+// hot loops hashing and cloning them.  Instead hand out a dense integer ID per name on
+// parse, so the rest of the code can use plain vectors indexed by ID.
+
+use std::collections::HashMap;
+
+pub type NameId = usize;
+
+#[derive(Debug, Clone, Default)]
+pub struct Interner {
+    ids: HashMap<String, NameId>,
+    names: Vec<String>,
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    // Get the ID for a name, allocating the next free one if it's new.
+    pub fn intern(&mut self, name: &str) -> NameId {
+        if let Some(&id) = self.ids.get(name) {
+            return id;
+        }
+        let id = self.names.len();
+        self.names.push(name.to_string());
+        self.ids.insert(name.to_string(), id);
+        id
+    }
+
+    // Get the ID for a name, if it's been interned.
+    pub fn get(&self, name: &str) -> Option<NameId> {
+        self.ids.get(name).copied()
+    }
+
+    pub fn name(&self, id: NameId) -> &str {
+        &self.names[id]
+    }
+
+    // Number of names so far.  IDs are always `0..len()`.
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    // All (ID, name) pairs, in ID order.
+    pub fn iter(&self) -> impl Iterator<Item = (NameId, &str)> + '_ {
+        self.names.iter().map(String::as_str).enumerate()
+    }
+}
@@ -0,0 +1,412 @@
+// Shared interval handling.
+// Day 5 (seed ranges pushed through the almanac) and day 19 (the xmas attribute
+// hyperrectangles) both spent most of their code hand-rolling range splitting, so
+// that lives here now.
+//
+// All intervals are half-open, `[start, end)`, which makes lengths and splitting
+// simple: splitting at `p` gives `[start, p)` and `[p, end)` with no +1/-1 fiddling.
+//
+// Not every day needs every operation, so some of this is only used by the tests.
+#![cfg_attr(not(test), allow(dead_code))]
+
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Interval {
+    pub start: i64,
+    pub end: i64,
+}
+
+impl Interval {
+    pub fn new(start: i64, end: i64) -> Self {
+        assert!(start <= end, "Invalid interval [{start}, {end})");
+        Interval { start, end }
+    }
+
+    // Build from a start and a length, as most AoC inputs give ranges.
+    pub fn with_len(start: i64, len: i64) -> Self {
+        Self::new(start, start + len)
+    }
+
+    pub fn len(&self) -> i64 {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start >= self.end
+    }
+
+    pub fn contains(&self, n: i64) -> bool {
+        n >= self.start && n < self.end
+    }
+
+    // The overlap of two intervals, if there is any.
+    pub fn intersect(&self, other: &Interval) -> Option<Interval> {
+        let start = self.start.max(other.start);
+        let end = self.end.min(other.end);
+        (start < end).then_some(Interval { start, end })
+    }
+
+    // Split into the parts before and from `point`.  Either side may be missing if
+    // the point is outside (or on the edge of) the interval.
+    pub fn split_at(&self, point: i64) -> (Option<Interval>, Option<Interval>) {
+        let point = point.clamp(self.start, self.end);
+        let before = Interval {
+            start: self.start,
+            end: point,
+        };
+        let after = Interval {
+            start: point,
+            end: self.end,
+        };
+        (
+            (!before.is_empty()).then_some(before),
+            (!after.is_empty()).then_some(after),
+        )
+    }
+
+    pub fn translate(&self, offset: i64) -> Interval {
+        Interval {
+            start: self.start + offset,
+            end: self.end + offset,
+        }
+    }
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}, {})", self.start, self.end)
+    }
+}
+
+// A set of integers stored as a list of intervals.
+// Always kept normalized: sorted, with no empty, overlapping or touching intervals,
+// so two sets with the same members compare equal.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct RangeSet {
+    intervals: Vec<Interval>,
+}
+
+impl RangeSet {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn from_intervals(intervals: impl IntoIterator<Item = Interval>) -> Self {
+        let mut intervals = intervals
+            .into_iter()
+            .filter(|i| !i.is_empty())
+            .collect::<Vec<_>>();
+        intervals.sort();
+
+        // Merge anything that overlaps or touches the previous interval.
+        let mut merged: Vec<Interval> = Vec::with_capacity(intervals.len());
+        for next in intervals {
+            match merged.last_mut() {
+                Some(last) if next.start <= last.end => last.end = last.end.max(next.end),
+                _ => merged.push(next),
+            }
+        }
+        RangeSet { intervals: merged }
+    }
+
+    pub fn intervals(&self) -> &[Interval] {
+        &self.intervals
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Interval> + '_ {
+        self.intervals.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.intervals.is_empty()
+    }
+
+    // Number of integers in the set.
+    pub fn total_len(&self) -> i64 {
+        self.intervals.iter().map(Interval::len).sum()
+    }
+
+    pub fn min(&self) -> Option<i64> {
+        self.intervals.first().map(|i| i.start)
+    }
+
+    pub fn max(&self) -> Option<i64> {
+        self.intervals.last().map(|i| i.end - 1)
+    }
+
+    pub fn contains(&self, n: i64) -> bool {
+        // Find the last interval starting at or before n.
+        let idx = self.intervals.partition_point(|i| i.start <= n);
+        idx > 0 && self.intervals[idx - 1].contains(n)
+    }
+
+    pub fn insert(&mut self, interval: Interval) {
+        *self = self.union(&RangeSet::from(interval));
+    }
+
+    pub fn union(&self, other: &RangeSet) -> RangeSet {
+        RangeSet::from_intervals(self.iter().chain(other.iter()).copied())
+    }
+
+    pub fn intersection(&self, other: &RangeSet) -> RangeSet {
+        // Both lists are sorted, so walk them together.
+        let mut result = vec![];
+        let (mut i, mut j) = (0, 0);
+        while i < self.intervals.len() && j < other.intervals.len() {
+            let (a, b) = (&self.intervals[i], &other.intervals[j]);
+            if let Some(overlap) = a.intersect(b) {
+                result.push(overlap);
+            }
+            // Move on whichever finishes first - it can't overlap anything else.
+            if a.end < b.end {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        RangeSet { intervals: result }
+    }
+
+    pub fn difference(&self, other: &RangeSet) -> RangeSet {
+        let mut result = vec![];
+        for interval in &self.intervals {
+            let mut remaining = Some(*interval);
+            for cut in other
+                .intervals
+                .iter()
+                .skip_while(|cut| cut.end <= interval.start)
+                .take_while(|cut| cut.start < interval.end)
+            {
+                let Some(current) = remaining else { break };
+                let (before, rest) = current.split_at(cut.start);
+                result.extend(before);
+                remaining = rest.and_then(|rest| rest.split_at(cut.end).1);
+            }
+            result.extend(remaining);
+        }
+        RangeSet { intervals: result }
+    }
+
+    // Split into the members below `point`, and those at or above it.
+    pub fn split_at(&self, point: i64) -> (RangeSet, RangeSet) {
+        let mut before = vec![];
+        let mut after = vec![];
+        for interval in &self.intervals {
+            let (b, a) = interval.split_at(point);
+            before.extend(b);
+            after.extend(a);
+        }
+        (
+            RangeSet { intervals: before },
+            RangeSet { intervals: after },
+        )
+    }
+
+    pub fn translate(&self, offset: i64) -> RangeSet {
+        RangeSet {
+            intervals: self.intervals.iter().map(|i| i.translate(offset)).collect(),
+        }
+    }
+}
+
+impl From<Interval> for RangeSet {
+    fn from(interval: Interval) -> Self {
+        RangeSet::from_intervals([interval])
+    }
+}
+
+impl FromIterator<Interval> for RangeSet {
+    fn from_iter<T: IntoIterator<Item = Interval>>(iter: T) -> Self {
+        RangeSet::from_intervals(iter)
+    }
+}
+
+impl fmt::Display for RangeSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{{")?;
+        for (idx, interval) in self.intervals.iter().enumerate() {
+            if idx > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{interval}")?;
+        }
+        write!(f, "}}")
+    }
+}
+
+// An N-dimensional box, with one interval per axis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct IntervalBox<const N: usize> {
+    pub dims: [Interval; N],
+}
+
+impl<const N: usize> IntervalBox<N> {
+    // A box with the same bounds on every axis.
+    pub fn cube(bounds: Interval) -> Self {
+        IntervalBox { dims: [bounds; N] }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dims.iter().any(Interval::is_empty)
+    }
+
+    // Number of integer points inside the box.
+    pub fn volume(&self) -> u64 {
+        self.dims.iter().map(|d| d.len().max(0) as u64).product()
+    }
+
+    pub fn contains(&self, point: [i64; N]) -> bool {
+        self.dims.iter().zip(point).all(|(d, p)| d.contains(p))
+    }
+
+    pub fn intersect(&self, other: &Self) -> Option<Self> {
+        let mut dims = self.dims;
+        for (dim, other_dim) in dims.iter_mut().zip(&other.dims) {
+            *dim = dim.intersect(other_dim)?;
+        }
+        Some(IntervalBox { dims })
+    }
+
+    // Split along one axis into the part below `point` and the part at or above it.
+    pub fn split_at(&self, axis: usize, point: i64) -> (Option<Self>, Option<Self>) {
+        let (before, after) = self.dims[axis].split_at(point);
+        let with_axis = |interval: Interval| {
+            let mut dims = self.dims;
+            dims[axis] = interval;
+            IntervalBox { dims }
+        };
+        (before.map(with_axis), after.map(with_axis))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(intervals: &[(i64, i64)]) -> RangeSet {
+        intervals
+            .iter()
+            .map(|&(s, e)| Interval::new(s, e))
+            .collect()
+    }
+
+    #[test]
+    fn normalizes_touching_and_overlapping() {
+        assert_eq!(
+            set(&[(5, 8), (0, 5)]).intervals(),
+            set(&[(0, 8)]).intervals()
+        );
+        assert_eq!(set(&[(0, 6), (3, 10), (12, 12)]), set(&[(0, 10)]));
+        assert!(set(&[(4, 4)]).is_empty());
+    }
+
+    #[test]
+    fn intersection() {
+        // Overlapping.
+        assert_eq!(
+            set(&[(0, 10)]).intersection(&set(&[(5, 15)])),
+            set(&[(5, 10)])
+        );
+        // Touching intervals don't share any members.
+        assert_eq!(set(&[(0, 5)]).intersection(&set(&[(5, 10)])), set(&[]));
+        // Several intervals on each side.
+        assert_eq!(
+            set(&[(0, 4), (6, 10), (12, 20)]).intersection(&set(&[(2, 7), (9, 13), (19, 30)])),
+            set(&[(2, 4), (6, 7), (9, 10), (12, 13), (19, 20)])
+        );
+        // Empty.
+        assert_eq!(set(&[(0, 10)]).intersection(&set(&[])), set(&[]));
+        assert_eq!(set(&[]).intersection(&set(&[(0, 10)])), set(&[]));
+    }
+
+    #[test]
+    fn difference() {
+        // Overlapping either end, and a hole in the middle.
+        assert_eq!(set(&[(0, 10)]).difference(&set(&[(8, 15)])), set(&[(0, 8)]));
+        assert_eq!(set(&[(5, 10)]).difference(&set(&[(0, 7)])), set(&[(7, 10)]));
+        assert_eq!(
+            set(&[(0, 10)]).difference(&set(&[(3, 5)])),
+            set(&[(0, 3), (5, 10)])
+        );
+        // Touching intervals take nothing away.
+        assert_eq!(set(&[(0, 5)]).difference(&set(&[(5, 10)])), set(&[(0, 5)]));
+        assert_eq!(set(&[(5, 10)]).difference(&set(&[(0, 5)])), set(&[(5, 10)]));
+        // Removing everything, or nothing.
+        assert_eq!(
+            set(&[(2, 4), (6, 8)]).difference(&set(&[(0, 10)])),
+            set(&[])
+        );
+        assert_eq!(set(&[(0, 10)]).difference(&set(&[])), set(&[(0, 10)]));
+        assert_eq!(set(&[]).difference(&set(&[(0, 10)])), set(&[]));
+        // One cut spanning several intervals, and several cuts in one interval.
+        assert_eq!(
+            set(&[(0, 4), (6, 10), (12, 20)]).difference(&set(&[(2, 13), (15, 16), (18, 19)])),
+            set(&[(0, 2), (13, 15), (16, 18), (19, 20)])
+        );
+    }
+
+    #[test]
+    fn split_at() {
+        let both = set(&[(0, 5), (10, 15)]);
+        assert_eq!(both.split_at(3), (set(&[(0, 3)]), set(&[(3, 5), (10, 15)])));
+        // On the edge of an interval, or in a gap.
+        assert_eq!(both.split_at(5), (set(&[(0, 5)]), set(&[(10, 15)])));
+        assert_eq!(both.split_at(10), (set(&[(0, 5)]), set(&[(10, 15)])));
+        assert_eq!(both.split_at(7), (set(&[(0, 5)]), set(&[(10, 15)])));
+        // Outside the set entirely.
+        assert_eq!(both.split_at(-1), (set(&[]), both.clone()));
+        assert_eq!(both.split_at(20), (both.clone(), set(&[])));
+        assert_eq!(set(&[]).split_at(0), (set(&[]), set(&[])));
+    }
+
+    #[test]
+    fn queries() {
+        let mut s = set(&[(0, 5), (10, 15)]);
+        assert_eq!(s.total_len(), 10);
+        assert_eq!((s.min(), s.max()), (Some(0), Some(14)));
+        assert!(s.contains(0) && s.contains(4) && s.contains(14));
+        assert!(!s.contains(-1) && !s.contains(5) && !s.contains(15));
+        s.insert(Interval::new(5, 8));
+        assert_eq!(s, set(&[(0, 8), (10, 15)]));
+        assert_eq!(s.translate(-10), set(&[(-10, -2), (0, 5)]));
+        assert_eq!((set(&[]).min(), set(&[]).max()), (None, None));
+    }
+
+    #[test]
+    fn interval_split_at() {
+        let interval = Interval::new(0, 10);
+        assert_eq!(
+            interval.split_at(4),
+            (Some(Interval::new(0, 4)), Some(Interval::new(4, 10)))
+        );
+        assert_eq!(interval.split_at(0), (None, Some(interval)));
+        assert_eq!(interval.split_at(10), (Some(interval), None));
+        assert_eq!(Interval::new(3, 3).split_at(3), (None, None));
+    }
+
+    #[test]
+    fn interval_box() {
+        let a = IntervalBox::<2>::cube(Interval::new(0, 10));
+        let b = IntervalBox {
+            dims: [Interval::new(5, 20), Interval::new(-5, 3)],
+        };
+        assert!(a.contains([0, 9]) && !a.contains([10, 0]));
+        assert_eq!(
+            a.intersect(&b),
+            Some(IntervalBox {
+                dims: [Interval::new(5, 10), Interval::new(0, 3)]
+            })
+        );
+        assert_eq!(a.intersect(&b).unwrap().volume(), 15);
+        // Boxes that only overlap on one axis don't overlap at all.
+        let c = IntervalBox {
+            dims: [Interval::new(0, 10), Interval::new(10, 20)],
+        };
+        assert_eq!(a.intersect(&c), None);
+        assert!(IntervalBox {
+            dims: [Interval::new(0, 10), Interval::new(4, 4)]
+        }
+        .is_empty());
+    }
+}
@@ -24,6 +24,13 @@ mod day23;
 mod day24;
 mod day25;
 
+mod intern;
+mod interval;
+mod memo;
+mod opts;
+mod parse;
+mod polygon;
+
 fn main() -> Result<(), String> {
     let day_num: u16 = std::env::args()
         .nth(1)
@@ -34,15 +41,15 @@ fn main() -> Result<(), String> {
         .nth(2)
         .expect("Must pass input file as second argument");
     match day_num {
-        1 => day01::run(input_path),
-        2 => day02::run(input_path),
-        3 => day03::run(input_path),
-        4 => day04::run(input_path),
-        5 => day05::run(input_path),
-        6 => day06::run(input_path),
-        7 => day07::run(input_path),
-        8 => day08::run(input_path),
-        9 => day09::run(input_path),
+        1 => day01::run(input_path)?,
+        2 => day02::run(input_path)?,
+        3 => day03::run(input_path)?,
+        4 => day04::run(input_path)?,
+        5 => day05::run(input_path)?,
+        6 => day06::run(input_path)?,
+        7 => day07::run(input_path)?,
+        8 => day08::run(input_path)?,
+        9 => day09::run(input_path)?,
         10 => day10::run(input_path),
         11 => day11::run(input_path),
         12 => day12::run(input_path),
@@ -52,13 +59,13 @@ fn main() -> Result<(), String> {
         16 => day16::run(input_path),
         17 => day17::run(input_path),
         18 => day18::run(input_path),
-        19 => day19::run(input_path),
-        20 => day20::run(input_path),
-        21 => day21::run(input_path),
-        22 => day22::run(input_path),
+        19 => day19::run(input_path)?,
+        20 => day20::run(input_path)?,
+        21 => day21::run(input_path)?,
+        22 => day22::run(input_path)?,
         23 => day23::run(input_path),
-        24 => day24::run(input_path),
-        25 => day25::run(input_path),
+        24 => day24::run(input_path)?,
+        25 => day25::run(input_path)?,
         _ => panic!("Day not implemented"),
     }
 
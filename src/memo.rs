@@ -0,0 +1,70 @@
+// Memoization for recursive functions.
+// Started life as day 12's hand-rolled cache.  The key can be anything hashable - for
+// speed prefer something cheap like a tuple of indices, but borrowed slices work too.
+// Hit, miss and size counts are kept since they're handy for checking the cache is
+// actually doing something.
+
+use std::{collections::HashMap, fmt, hash::Hash};
+
+#[derive(Debug, Clone)]
+pub struct Memo<K, V> {
+    cache: HashMap<K, V>,
+    stats: MemoStats,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoStats {
+    pub hits: u64,
+    pub misses: u64,
+    // Largest number of entries held at once.
+    pub max_size: usize,
+}
+
+impl<K, V> Default for Memo<K, V> {
+    fn default() -> Self {
+        Memo {
+            cache: HashMap::new(),
+            stats: Default::default(),
+        }
+    }
+}
+
+impl<K: Hash + Eq, V: Clone> Memo<K, V> {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    // Look up the result for `key`, or calculate it with `f` and remember it.
+    // `f` gets the memo back so it can make its own (memoized) recursive calls.
+    pub fn call(&mut self, key: K, f: impl FnOnce(&mut Self) -> V) -> V {
+        if let Some(res) = self.cache.get(&key) {
+            self.stats.hits += 1;
+            return res.clone();
+        }
+        self.stats.misses += 1;
+        let res = f(self);
+        self.cache.insert(key, res.clone());
+        self.stats.max_size = self.stats.max_size.max(self.cache.len());
+        res
+    }
+
+    // Forget the cached results, eg because the keys are about to mean something else.
+    // Stats carry on accumulating.
+    pub fn clear(&mut self) {
+        self.cache.clear();
+    }
+
+    pub fn stats(&self) -> MemoStats {
+        self.stats
+    }
+}
+
+impl fmt::Display for MemoStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} hits {} misses, at most {} entries",
+            self.hits, self.misses, self.max_size
+        )
+    }
+}
@@ -0,0 +1,28 @@
+// Extra command line options, for days that have more than one way to run.
+// Anything after the input file is an option: either a plain `--flag`, or `--name=value`.
+//    cargo run 25 input.txt --verbose
+// Days look up what they care about directly, so `run` keeps the same signature everywhere.
+
+fn options() -> impl Iterator<Item = String> {
+    std::env::args().skip(3)
+}
+
+// Was `--name` passed?
+pub fn flag(name: &str) -> bool {
+    options().any(|arg| arg.strip_prefix("--") == Some(name))
+}
+
+// The value from `--name=value`, if it was passed.
+pub fn value(name: &str) -> Option<String> {
+    options().find_map(|arg| {
+        arg.strip_prefix("--")?
+            .strip_prefix(name)?
+            .strip_prefix('=')
+            .map(String::from)
+    })
+}
+
+// `--verbose`, or `-v` for short.
+pub fn verbose() -> bool {
+    flag("verbose") || options().any(|arg| arg == "-v")
+}
@@ -0,0 +1,227 @@
+// Helpers for the parsing patterns that come up nearly every day.
+// Everything works on a `Span`: a slice of the input that remembers the input it came from,
+// so when the input isn't what we expect the error can say which line and column.
+// (The position is only worked out when there's an error - doing it for every span made
+// splitting a big input into lines quadratic.)
+//
+// The input is still assumed to be mostly well-formed - this isn't trying to be a
+// parser combinator library, just to save writing `split_once(..).unwrap()` everywhere.
+
+use std::{fmt, str::FromStr};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub col: usize,
+    pub msg: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}: {}", self.line, self.col, self.msg)
+    }
+}
+
+// So days can use `?` straight into `main`'s error type.
+impl From<ParseError> for String {
+    fn from(err: ParseError) -> Self {
+        err.to_string()
+    }
+}
+
+pub type ParseResult<T> = Result<T, ParseError>;
+
+// Some text, plus the whole input it's part of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span<'a> {
+    pub text: &'a str,
+    input: &'a str,
+}
+
+impl<'a> Span<'a> {
+    // The whole input.
+    pub fn new(text: &'a str) -> Self {
+        Span { text, input: text }
+    }
+
+    pub fn as_str(&self) -> &'a str {
+        self.text
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    // Where this span starts in the input, as (line, column), both 1-indexed.
+    fn position(&self) -> (usize, usize) {
+        let before = &self.input[..self.text.as_ptr() as usize - self.input.as_ptr() as usize];
+        let line_start = before.rfind('\n').map_or(0, |nl| nl + 1);
+        (
+            before.matches('\n').count() + 1,
+            before[line_start..].chars().count() + 1,
+        )
+    }
+
+    pub fn error(&self, msg: impl Into<String>) -> ParseError {
+        let (line, col) = self.position();
+        ParseError {
+            line,
+            col,
+            msg: msg.into(),
+        }
+    }
+
+    // The part of this span between two byte offsets.
+    fn sub(&self, start: usize, end: usize) -> Span<'a> {
+        Span {
+            text: &self.text[start..end],
+            input: self.input,
+        }
+    }
+
+    // Byte offset of a slice of our text.
+    fn offset_of(&self, part: &str) -> usize {
+        part.as_ptr() as usize - self.text.as_ptr() as usize
+    }
+
+    fn span_of(&self, part: &'a str) -> Span<'a> {
+        let start = self.offset_of(part);
+        self.sub(start, start + part.len())
+    }
+
+    pub fn trim(&self) -> Span<'a> {
+        self.span_of(self.text.trim())
+    }
+
+    pub fn lines(&self) -> impl Iterator<Item = Span<'a>> + '_ {
+        self.text.lines().map(|l| self.span_of(l))
+    }
+
+    // Blocks of lines separated by a blank line.
+    pub fn sections(&self) -> impl Iterator<Item = Span<'a>> + '_ {
+        self.text
+            .split("\n\n")
+            .map(|s| self.span_of(s).trim())
+            .filter(|s| !s.is_empty())
+    }
+
+    pub fn split(&self, sep: &'a str) -> impl Iterator<Item = Span<'a>> + '_ {
+        self.text.split(sep).map(|s| self.span_of(s))
+    }
+
+    pub fn words(&self) -> impl Iterator<Item = Span<'a>> + '_ {
+        self.text.split_whitespace().map(|w| self.span_of(w))
+    }
+
+    // Each character as its own span.
+    pub fn chars(&self) -> impl Iterator<Item = Span<'a>> + '_ {
+        self.text
+            .char_indices()
+            .map(|(idx, c)| self.sub(idx, idx + c.len_utf8()))
+    }
+
+    // Split around the first `sep`, which must be there.
+    pub fn split_once(&self, sep: &str) -> ParseResult<(Span<'a>, Span<'a>)> {
+        let idx = self
+            .text
+            .find(sep)
+            .ok_or_else(|| self.error(format!("expected '{sep}' in '{}'", self.text)))?;
+        Ok((self.sub(0, idx), self.sub(idx + sep.len(), self.text.len())))
+    }
+
+    // Strip a prefix, which must be there.
+    pub fn expect_prefix(&self, prefix: &str) -> ParseResult<Span<'a>> {
+        if self.text.starts_with(prefix) {
+            Ok(self.sub(prefix.len(), self.text.len()))
+        } else {
+            Err(self.error(format!("expected '{prefix}'")))
+        }
+    }
+
+    pub fn parse<T: FromStr>(&self) -> ParseResult<T>
+    where
+        T::Err: fmt::Display,
+    {
+        self.text
+            .parse()
+            .map_err(|e| self.error(format!("invalid value '{}': {e}", self.text)))
+    }
+
+    // A list of numbers separated by whitespace and/or commas, like `1 -2 3` or `4,5,+6`.
+    pub fn numbers<T: FromStr>(&self) -> ParseResult<Vec<T>>
+    where
+        T::Err: fmt::Display,
+    {
+        self.text
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|s| !s.is_empty())
+            .map(|s| self.span_of(s).parse())
+            .collect()
+    }
+
+    // A `Label: rest` line, giving the label and the (trimmed) rest.
+    pub fn labelled(&self) -> ParseResult<(Span<'a>, Span<'a>)> {
+        let (label, rest) = self.split_once(":")?;
+        Ok((label.trim(), rest.trim()))
+    }
+
+    // A record like `x=1,m=2`, split into (key, value) pairs.
+    pub fn key_values(&self, sep: &'a str, assign: &str) -> ParseResult<Vec<(Span<'a>, Span<'a>)>> {
+        self.split(sep)
+            .map(|entry| {
+                let (k, v) = entry.trim().split_once(assign)?;
+                Ok((k.trim(), v.trim()))
+            })
+            .collect()
+    }
+
+    // Match against a pattern where each `{}` captures text, and everything else must
+    // match exactly.  A capture runs up to the first occurrence of the literal text
+    // after it, or to the end for a trailing `{}`.
+    //    let [src, left, right] = line.captures("{} = ({}, {})")?;
+    pub fn captures<const N: usize>(&self, pattern: &str) -> ParseResult<[Span<'a>; N]> {
+        let literals = pattern.split("{}").collect::<Vec<_>>();
+        assert_eq!(literals.len(), N + 1, "Pattern needs {N} captures");
+
+        let mut rest = self.expect_prefix(literals[0])?;
+        let mut captures = [*self; N];
+        for (idx, literal) in literals[1..].iter().enumerate() {
+            let is_last = idx + 1 == N;
+            let (capture, after) = if literal.is_empty() && is_last {
+                (rest, rest.sub(rest.text.len(), rest.text.len()))
+            } else {
+                rest.split_once(literal)?
+            };
+            captures[idx] = capture;
+            rest = after;
+        }
+        if !rest.is_empty() {
+            return Err(rest.error(format!("unexpected trailing '{}'", rest.text)));
+        }
+        Ok(captures)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors_are_located() {
+        let input = "first line\nkey = (a, b)\n  ünï: 1 2 x";
+        let lines = Span::new(input).lines().collect::<Vec<_>>();
+
+        let err = lines[0].error("oops");
+        assert_eq!((err.line, err.col), (1, 1));
+
+        let [_, _, right] = lines[1].captures::<3>("{} = ({}, {})").unwrap();
+        assert_eq!(right.as_str(), "b");
+        let err = right.error("oops");
+        assert_eq!((err.line, err.col), (2, 11));
+
+        // Columns count characters, not bytes.
+        let (_, numbers) = lines[2].labelled().unwrap();
+        let err = numbers.numbers::<u32>().unwrap_err();
+        assert_eq!((err.line, err.col), (3, 12));
+    }
+}
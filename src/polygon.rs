@@ -0,0 +1,131 @@
+// Area of a polygon drawn on the integer lattice.
+// Day 18 found this via the Shoelace formula and Pick's theorem, and day 10's loop is the
+// same problem in disguise, so both share this.
+//  - Shoelace (https://en.wikipedia.org/wiki/Shoelace_formula) gives twice the area.
+//  - Each edge contributes gcd(|dx|, |dy|) lattice points to the boundary.
+//  - Pick's theorem (https://en.wikipedia.org/wiki/Pick%27s_theorem) then gives the
+//    interior points: A = I + B/2 - 1.
+// Everything is signed, so paths can wander into negative coordinates.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PolygonArea {
+    // Kept doubled, since a general lattice polygon can have half-integer area.
+    pub double_area: i64,
+    pub boundary: i64,
+    pub interior: i64,
+}
+
+impl PolygonArea {
+    // Every lattice point on or inside the boundary.
+    pub fn enclosed_points(&self) -> i64 {
+        self.interior + self.boundary
+    }
+}
+
+// Measure the polygon through the given vertices, in order.
+// The path is treated as closed whether or not the last vertex repeats the first.
+pub fn measure(vertices: &[(i64, i64)]) -> PolygonArea {
+    let edges = vertices
+        .iter()
+        .zip(vertices.iter().cycle().skip(1))
+        .take(vertices.len());
+
+    let (signed_double_area, boundary) =
+        edges.fold((0, 0), |(area, boundary), (&(x1, y1), &(x2, y2))| {
+            (
+                area + x1 * y2 - x2 * y1,
+                boundary + num::integer::gcd((x2 - x1).abs(), (y2 - y1).abs()),
+            )
+        });
+
+    // The sign depends on which way round we went, which we don't care about.
+    let double_area = signed_double_area.abs();
+    PolygonArea {
+        double_area,
+        boundary,
+        // Pick's theorem, rearranged to stay in integers: 2I = 2A - B + 2.
+        interior: (double_area - boundary + 2) / 2,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unit_square() {
+        let area = measure(&[(0, 0), (1, 0), (1, 1), (0, 1)]);
+        assert_eq!(
+            area,
+            PolygonArea {
+                double_area: 2,
+                boundary: 4,
+                interior: 0
+            }
+        );
+        // Going round the other way, or repeating the first vertex, makes no difference.
+        assert_eq!(measure(&[(0, 0), (0, 1), (1, 1), (1, 0), (0, 0)]), area);
+    }
+
+    #[test]
+    fn l_shape() {
+        // A 4x4 square with a 2x2 bite out of one corner.  The inside points are the three
+        // along the bottom arm and two more up the side.
+        let area = measure(&[(0, 0), (4, 0), (4, 2), (2, 2), (2, 4), (0, 4)]);
+        assert_eq!(
+            area,
+            PolygonArea {
+                double_area: 24,
+                boundary: 16,
+                interior: 5
+            }
+        );
+        // Moving it into negative coordinates doesn't change anything.
+        assert_eq!(
+            measure(&[(-9, -9), (-5, -9), (-5, -7), (-7, -7), (-7, -5), (-9, -5)]),
+            area
+        );
+    }
+
+    #[test]
+    fn day18_example() {
+        let steps = [
+            ('R', 6),
+            ('D', 5),
+            ('L', 2),
+            ('D', 2),
+            ('R', 2),
+            ('D', 2),
+            ('L', 5),
+            ('U', 2),
+            ('L', 1),
+            ('U', 2),
+            ('R', 2),
+            ('U', 3),
+            ('L', 2),
+            ('U', 2),
+        ];
+        let vertices = steps
+            .iter()
+            .scan((0, 0), |(x, y), &(dir, len)| {
+                match dir {
+                    'R' => *x += len,
+                    'L' => *x -= len,
+                    'D' => *y += len,
+                    _ => *y -= len,
+                }
+                Some((*x, *y))
+            })
+            .collect::<Vec<_>>();
+        let area = measure(&vertices);
+        assert_eq!(
+            area,
+            PolygonArea {
+                double_area: 84,
+                boundary: 38,
+                interior: 24
+            }
+        );
+        assert_eq!(area.enclosed_points(), 62);
+    }
+}